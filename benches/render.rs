@@ -0,0 +1,37 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use nns_chart_parser::{chart::Chart, parser::ShorthandParser, renderer::ChordRenderer};
+
+/// A songbook-sized chart: `sections` sections of eight four-bar lines,
+/// with split bars, repeats and spacers.
+fn songbook(sections: usize) -> Chart {
+    let mut source = String::from("title: Songbook\nkey: E\n");
+    for section in 1..=sections {
+        source.push_str(&format!("\n[Section {}]\n", section));
+        for _ in 0..4 {
+            source.push_str("||: 1 4_5 6m 5 :||\n");
+            source.push_str("2m7 . 4/6 5sus4_5 1\n");
+        }
+    }
+    ShorthandParser::parse_str(&source).unwrap()
+}
+
+fn render_chart(c: &mut Criterion) {
+    let mut group = c.benchmark_group("render_chart");
+    // 256 and 512 measures
+    for sections in [8, 16] {
+        let chart = songbook(sections);
+        group.bench_function(format!("{} measures", sections * 32), |b| {
+            b.iter(|| {
+                let mut renderer = ChordRenderer::new();
+                renderer
+                    .init_background()
+                    .render_chart(black_box(&chart), 100, 200);
+                renderer
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, render_chart);
+criterion_main!(benches);
@@ -0,0 +1,264 @@
+use crate::form::Form;
+use crate::line::{ChordData, ChordDef, Line, LineElement};
+use crate::numbering::MeasureNumbers;
+use crate::theory::{Key, MinorNumbering, Mode};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Section {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lines: Vec<Line>,
+    /// Name of an earlier section this one repeats. The parser copies the
+    /// referenced lines in, so `lines` is always populated after parsing.
+    #[serde(rename = "ref", default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+    /// Replaces the last measure of the referenced section for this instance.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_measure: Option<Vec<ChordDef>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TimeSignature {
+    pub beats: u32,
+    pub unit: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Chart {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<Key>,
+    /// The chart's mode (ionian, dorian, ...); overrides the key's own mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<Mode>,
+    /// Whether a minor-key chart counts from the minor tonic (1m) or from
+    /// its relative major (6m).
+    #[serde(default, skip_serializing_if = "MinorNumbering::is_tonic")]
+    pub minor_numbering: MinorNumbering,
+    #[serde(default, skip_serializing_if = "TimeSignature::is_common_time")]
+    pub time: TimeSignature,
+    /// Tempo in beats per minute, counting the time signature's unit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tempo: Option<u32>,
+    /// Fret a capo sits on. Chord numbers are unchanged; only the letter
+    /// names of the shapes played differ from the sounding chords.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capo: Option<u32>,
+    pub sections: Vec<Section>,
+}
+
+impl Default for TimeSignature {
+    fn default() -> Self {
+        Self { beats: 4, unit: 4 }
+    }
+}
+
+impl TimeSignature {
+    /// 4/4, the default.
+    pub fn is_common_time(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl fmt::Display for TimeSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.beats, self.unit)
+    }
+}
+
+impl FromStr for TimeSignature {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid time signature: {}", s);
+        let (beats, unit) = s.trim().split_once('/').ok_or_else(invalid)?;
+        let beats: u32 = beats.trim().parse().map_err(|_| invalid())?;
+        let unit: u32 = unit.trim().parse().map_err(|_| invalid())?;
+        // The same units the schema allows: whole notes to 32nds
+        if beats == 0 || !unit.is_power_of_two() || unit > 32 {
+            return Err(invalid());
+        }
+        Ok(Self { beats, unit })
+    }
+}
+
+impl TryFrom<String> for TimeSignature {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<TimeSignature> for String {
+    fn from(time: TimeSignature) -> Self {
+        time.to_string()
+    }
+}
+
+impl Section {
+    pub fn new(name: &str) -> Self {
+        Self::with_lines(name, Vec::new())
+    }
+
+    pub fn with_lines(name: &str, lines: Vec<Line>) -> Self {
+        Self {
+            name: name.to_string(),
+            lines,
+            reference: None,
+            last_measure: None,
+        }
+    }
+
+    /// A section that repeats the earlier section called `name`.
+    pub fn reference_to(name: &str) -> Self {
+        Self {
+            reference: Some(name.to_string()),
+            ..Self::new(name)
+        }
+    }
+
+    pub fn is_reference(&self) -> bool {
+        self.reference.is_some()
+    }
+
+    pub fn add_line(&mut self, line: Line) {
+        self.lines.push(line);
+    }
+}
+
+impl Default for Chart {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Chart {
+    pub fn new() -> Self {
+        Self::with_sections(Vec::new())
+    }
+
+    pub fn with_sections(sections: Vec<Section>) -> Self {
+        Self {
+            title: None,
+            key: None,
+            mode: None,
+            minor_numbering: MinorNumbering::Tonic,
+            time: TimeSignature::default(),
+            tempo: None,
+            capo: None,
+            sections,
+        }
+    }
+
+    pub fn add_section(&mut self, section: Section) {
+        self.sections.push(section);
+    }
+
+    /// Derives the road map ("I V C V C B C C O") from the section order.
+    pub fn form(&self) -> Form {
+        Form::from_chart(self)
+    }
+
+    pub fn measure_numbers(&self) -> MeasureNumbers {
+        MeasureNumbers::from_chart(self)
+    }
+
+    /// The chart's mode: the declared `mode`, else the key's, else major.
+    pub fn mode(&self) -> Mode {
+        self.mode
+            .or(self.key.map(|key| key.mode))
+            .unwrap_or_default()
+    }
+
+    /// The key letter names are spelled from, taking the chart's mode and
+    /// minor numbering convention into account. `key` overrides the chart's
+    /// own.
+    pub fn letter_key(&self, key: Option<Key>) -> Option<Key> {
+        self.sounding_key(key)
+            .map(|key| key.numbering_key(self.minor_numbering))
+    }
+
+    /// The key the chart is in, with the chart's mode: "Am" even where the
+    /// numbers count from C. `key` overrides the chart's own.
+    pub fn sounding_key(&self, key: Option<Key>) -> Option<Key> {
+        key.or(self.key)
+            .map(|key| Key::with_mode(key.tonic, self.mode.unwrap_or(key.mode)))
+    }
+
+    /// The key the capo shapes are spelled in, for a chart with a capo.
+    /// `key` overrides the chart's sounding key as in `letter_key`.
+    pub fn shapes_key(&self, key: Option<Key>) -> Option<Key> {
+        let capo = self.capo?;
+        self.letter_key(key).map(|key| key.capo_shapes(capo))
+    }
+
+    /// The mode chord numbers are diatonic to: the chart's mode, or major
+    /// for a relatively numbered minor chart, which counts from the relative
+    /// major.
+    pub fn diatonic_mode(&self) -> Mode {
+        match self.minor_numbering {
+            MinorNumbering::Relative => Mode::Major,
+            MinorNumbering::Tonic => self.mode(),
+        }
+    }
+
+    /// Fills in the diatonic quality of every chord written without one, so
+    /// "2" is minor in a major chart and "4" is major in dorian.
+    pub fn infer_qualities(&mut self) {
+        let mode = self.diatonic_mode();
+        for chord in self.chords_mut() {
+            if chord.quality.is_empty() {
+                chord.quality = mode
+                    .diatonic_quality(chord.degree, chord.accidental)
+                    .name()
+                    .to_string();
+            }
+        }
+    }
+
+    /// Re-numbers every chord, including slash bass notes and last-measure
+    /// overrides, into the given minor-key convention.
+    pub fn renumber(&mut self, numbering: MinorNumbering) {
+        let from = self.minor_numbering;
+        for chord in self.chords_mut() {
+            (chord.degree, chord.accidental) =
+                from.convert(numbering, chord.degree, chord.accidental);
+            if let Some(bass) = &mut chord.bass {
+                (bass.degree, bass.accidental) =
+                    from.convert(numbering, bass.degree, bass.accidental);
+            }
+        }
+        self.minor_numbering = numbering;
+    }
+
+    /// Every chord in the chart, including last-measure overrides.
+    fn chords_mut(&mut self) -> impl Iterator<Item = &mut ChordData> {
+        self.sections.iter_mut().flat_map(|section| {
+            let line_chords = section
+                .lines
+                .iter_mut()
+                .flat_map(|line| &mut line.line)
+                .filter_map(|element| match element {
+                    LineElement::Measure { measure, .. } => Some(measure),
+                    _ => None,
+                })
+                .flatten();
+            let override_chords = section.last_measure.iter_mut().flatten();
+            line_chords.chain(override_chords).map(|def| &mut def.chord)
+        })
+    }
+}
+
+/// A bare line file becomes a chart with a single unnamed section.
+impl From<Line> for Chart {
+    fn from(line: Line) -> Self {
+        Self::with_sections(vec![Section::with_lines("", vec![line])])
+    }
+}
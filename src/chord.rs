@@ -1,7 +1,9 @@
 use serde::{Deserialize, Serialize};
+use std::fmt;
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
 pub enum ChordQuality {
+    #[default]
     #[serde(rename = "major")]
     Major,
     #[serde(rename = "minor")]
@@ -16,8 +18,158 @@ pub enum ChordQuality {
     Dim,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Accidental {
+    Flat,
+    Sharp,
+}
+
+/// The bass note of a slash chord, written as a degree ("3") or an altered
+/// degree ("b7").
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(try_from = "BassValue", into = "BassValue")]
+pub struct Bass {
+    pub degree: i32,
+    pub accidental: Option<Accidental>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum BassValue {
+    Degree(i32),
+    Altered(String),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
 pub struct Chord {
     pub degree: i32,
     pub quality: ChordQuality,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accidental: Option<Accidental>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bass: Option<Bass>,
+    /// Sevenths, added tones and the like, written after the quality
+    /// ("7", "maj7", "add9").
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extension: Option<String>,
+    /// Played an eighth early, ahead of the beat (a "push").
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub push: bool,
+}
+
+impl ChordQuality {
+    /// The name used in chart files, e.g. "minor".
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChordQuality::Major => "major",
+            ChordQuality::Minor => "minor",
+            ChordQuality::Sus2 => "sus2",
+            ChordQuality::Sus4 => "sus4",
+            ChordQuality::Aug => "aug",
+            ChordQuality::Dim => "dim",
+        }
+    }
+
+    /// The suffix used in chord symbols, e.g. "m" in "Am" or "6m".
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            ChordQuality::Major => "",
+            ChordQuality::Minor => "m",
+            ChordQuality::Sus2 => "sus2",
+            ChordQuality::Sus4 => "sus4",
+            ChordQuality::Aug => "aug",
+            ChordQuality::Dim => "dim",
+        }
+    }
+}
+
+impl Accidental {
+    /// Semitone offset from the unaltered degree.
+    pub fn semitones(&self) -> i32 {
+        match self {
+            Accidental::Flat => -1,
+            Accidental::Sharp => 1,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Accidental::Flat => "b",
+            Accidental::Sharp => "#",
+        }
+    }
+}
+
+impl Bass {
+    pub fn new(degree: i32) -> Self {
+        Self {
+            degree,
+            accidental: None,
+        }
+    }
+}
+
+impl fmt::Display for Bass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let accidental = self.accidental.as_ref().map_or("", Accidental::symbol);
+        write!(f, "{}{}", accidental, self.degree)
+    }
+}
+
+impl TryFrom<BassValue> for Bass {
+    type Error = String;
+
+    fn try_from(value: BassValue) -> Result<Self, Self::Error> {
+        let (accidental, degree) = match value {
+            BassValue::Degree(degree) => (None, degree),
+            BassValue::Altered(text) => {
+                let invalid = || format!("invalid bass degree: {}", text);
+                let (accidental, digits) = match text.chars().next() {
+                    Some('b') => (Some(Accidental::Flat), &text[1..]),
+                    Some('#') => (Some(Accidental::Sharp), &text[1..]),
+                    _ => (None, text.as_str()),
+                };
+                (accidental, digits.parse().map_err(|_| invalid())?)
+            }
+        };
+        if !(1..=7).contains(&degree) {
+            return Err(format!("invalid bass degree: {}", degree));
+        }
+        Ok(Self { degree, accidental })
+    }
+}
+
+impl From<Bass> for BassValue {
+    fn from(bass: Bass) -> Self {
+        match bass.accidental {
+            None => BassValue::Degree(bass.degree),
+            Some(_) => BassValue::Altered(bass.to_string()),
+        }
+    }
+}
+
+impl Chord {
+    pub fn new(degree: i32, quality: ChordQuality) -> Self {
+        Self {
+            degree,
+            quality,
+            ..Default::default()
+        }
+    }
+}
+
+/// The chord as numbers, e.g. "b7", "6m7" or "5/7".
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let accidental = self.accidental.as_ref().map_or("", Accidental::symbol);
+        write!(f, "{}{}{}", accidental, self.degree, self.quality.suffix())?;
+        if let Some(extension) = &self.extension {
+            write!(f, "{}", extension)?;
+        }
+        if let Some(bass) = &self.bass {
+            write!(f, "/{}", bass)?;
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,489 @@
+use crate::chart::{Chart, Section};
+use crate::chord::{Bass, Chord, ChordQuality};
+use crate::line::{Annotation, AnnotationKind, ChordData, ChordDef, Line, LineElement, RepeatSign};
+use crate::shorthand::parse_suffix;
+use crate::theory::{Key, Mode, Note};
+use anyhow::Result;
+
+/// Pieces of a ChordPro lyric line, in order.
+enum Token<'a> {
+    Chord(&'a str),
+    Lyric(&'a str),
+    Bar,
+}
+
+struct Importer {
+    chart: Chart,
+    current: Option<usize>,
+    /// Inside `{start_of_verse}` and the like, as opposed to after a comment.
+    in_block: bool,
+    last_chorus: Option<String>,
+    chords_per_measure: Option<usize>,
+    /// Inside a block such as `{start_of_tab}` whose lines are not chords.
+    skipping: bool,
+}
+
+/// Imports a ChordPro song. Letter chords are numbered relative to the
+/// `{key}` directive, which must come before the first chord.
+///
+/// Each chord becomes a measure of its own unless the lyric line marks bars
+/// with `|`, or `{x_chords_per_measure: N}` groups every N chords. Lyrics
+/// are kept as lyric annotations on the measure they are sung over.
+/// `{start_of_verse}`, `{start_of_chorus}` and `{start_of_bridge}` (with an
+/// optional label) start sections, as does a `{comment}` outside of one;
+/// `{chorus}` repeats the last chorus.
+pub fn import(text: &str) -> Result<Chart> {
+    let mut importer = Importer {
+        chart: Chart::new(),
+        current: None,
+        in_block: false,
+        last_chorus: None,
+        chords_per_measure: None,
+        skipping: false,
+    };
+
+    for (index, raw) in text.lines().enumerate() {
+        let trimmed = raw.trim();
+        let result = if trimmed.starts_with('{') && trimmed.ends_with('}') {
+            importer.directive(&trimmed[1..trimmed.len() - 1])
+        } else if trimmed.is_empty() || trimmed.starts_with('#') || importer.skipping {
+            Ok(())
+        } else {
+            importer.lyric_line(trimmed)
+        };
+        result.map_err(|message| {
+            anyhow::anyhow!(
+                "Failed to parse ChordPro at line {}: {}",
+                index + 1,
+                message
+            )
+        })?;
+    }
+
+    // Lyric-only sections have nothing to chart, nor do repeats of them
+    let sections = &mut importer.chart.sections;
+    sections.retain(|section| section.is_reference() || !section.lines.is_empty());
+    let defined: Vec<String> = sections
+        .iter()
+        .filter(|section| !section.is_reference())
+        .map(|section| section.name.clone())
+        .collect();
+    sections.retain(|section| {
+        section
+            .reference
+            .as_ref()
+            .is_none_or(|reference| defined.contains(reference))
+    });
+
+    Ok(importer.chart)
+}
+
+impl Importer {
+    fn directive(&mut self, directive: &str) -> Result<(), String> {
+        let (name, value) = match directive.split_once(':') {
+            Some((name, value)) => (name.trim(), value.trim()),
+            None => (directive.trim(), ""),
+        };
+        let label = |default: &str| {
+            if value.is_empty() {
+                default.to_string()
+            } else {
+                value.to_string()
+            }
+        };
+
+        match name.to_ascii_lowercase().as_str() {
+            "title" | "t" => self.chart.title = Some(value.to_string()),
+            "key" => self.chart.key = Some(value.parse()?),
+            "meta" => {
+                // The mode of a modal key, as exported
+                if let (Some(("mode", mode)), Some(key)) =
+                    (value.split_once(' '), self.chart.key.as_mut())
+                {
+                    key.mode = mode.trim().parse()?;
+                }
+            }
+            "time" => self.chart.time = value.parse()?,
+            "tempo" => {
+                let tempo = value
+                    .parse()
+                    .map_err(|_| format!("invalid tempo: {}", value))?;
+                self.chart.tempo = Some(tempo);
+            }
+            "capo" => {
+                let capo = value
+                    .parse()
+                    .map_err(|_| format!("invalid capo: {}", value))?;
+                self.chart.capo = Some(capo);
+            }
+            "x_chords_per_measure" => {
+                let count = value
+                    .parse()
+                    .ok()
+                    .filter(|count| *count > 0)
+                    .ok_or_else(|| format!("invalid chords per measure: {}", value))?;
+                self.chords_per_measure = Some(count);
+            }
+            "start_of_verse" | "sov" => self.start_block(label("Verse")),
+            "start_of_bridge" | "sob" => self.start_block(label("Bridge")),
+            "start_of_chorus" | "soc" => {
+                let name = label("Chorus");
+                self.last_chorus = Some(name.clone());
+                self.start_block(name);
+            }
+            "end_of_verse" | "eov" | "end_of_bridge" | "eob" | "end_of_chorus" | "eoc" => {
+                self.current = None;
+                self.in_block = false;
+            }
+            "chorus" => {
+                let name = self.last_chorus.clone().unwrap_or_else(|| label("Chorus"));
+                self.chart.add_section(Section::reference_to(&name));
+                self.current = None;
+            }
+            // A comment outside a block names the lines that follow it
+            "comment" | "c" | "comment_italic" | "ci" if !self.in_block => {
+                self.start_section(value.to_string());
+            }
+            _ if name.starts_with("start_of_") || name == "sot" || name == "sog" => {
+                self.skipping = true;
+            }
+            _ if name.starts_with("end_of_") || name == "eot" || name == "eog" => {
+                self.skipping = false;
+            }
+            // Metadata and formatting directives have no place in a chart
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn start_block(&mut self, name: String) {
+        self.start_section(name);
+        self.in_block = true;
+    }
+
+    fn start_section(&mut self, name: String) {
+        self.chart.add_section(Section::new(&name));
+        self.current = Some(self.chart.sections.len() - 1);
+    }
+
+    fn lyric_line(&mut self, text: &str) -> Result<(), String> {
+        let tokens = tokenize(text)?;
+        if !tokens.iter().any(|token| matches!(token, Token::Chord(_))) {
+            return Ok(());
+        }
+        let key = self
+            .chart
+            .key
+            .ok_or_else(|| "chords need a {key} directive before them".to_string())?;
+
+        // Measures as (chords, lyrics), split at bar lines, "N.C." and
+        // either every chord or every `chords_per_measure` chords
+        let barred = tokens.iter().any(|token| matches!(token, Token::Bar));
+        let per_measure = if barred {
+            usize::MAX
+        } else {
+            self.chords_per_measure.unwrap_or(1)
+        };
+        let mut line = Line::new();
+        let mut chords: Vec<ChordDef> = Vec::new();
+        let mut lyrics = String::new();
+
+        for token in tokens {
+            match token {
+                Token::Lyric(text) => lyrics.push_str(text),
+                Token::Bar => flush(&mut line, &mut chords, &mut lyrics),
+                Token::Chord(symbol) if is_no_chord(symbol) => {
+                    flush(&mut line, &mut chords, &mut lyrics);
+                    line.add_element(LineElement::Spacer);
+                }
+                Token::Chord(symbol) => {
+                    if chords.len() >= per_measure {
+                        flush(&mut line, &mut chords, &mut lyrics);
+                    }
+                    chords.push(ChordDef {
+                        chord: letter_chord(symbol, &key)?,
+                    });
+                }
+            }
+        }
+        flush(&mut line, &mut chords, &mut lyrics);
+
+        let index = match self.current {
+            Some(index) => index,
+            None => {
+                self.start_section(String::new());
+                self.chart.sections.len() - 1
+            }
+        };
+        self.chart.sections[index].add_line(line);
+        Ok(())
+    }
+}
+
+/// Ends the measure being built, if any, attaching the lyrics sung over it.
+/// Lyrics before a line's first chord wait for the first measure.
+fn flush(line: &mut Line, chords: &mut Vec<ChordDef>, lyrics: &mut String) {
+    if chords.is_empty() {
+        return;
+    }
+    line.add_element(LineElement::Measure {
+        measure: std::mem::take(chords),
+        pickup: None,
+    });
+    let text = lyrics.split_whitespace().collect::<Vec<_>>().join(" ");
+    if !text.is_empty() {
+        line.add_element(LineElement::Annotation {
+            annotation: Annotation {
+                text,
+                kind: AnnotationKind::Lyric,
+                chord: None,
+            },
+        });
+    }
+    lyrics.clear();
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token<'_>>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('[') {
+            let (symbol, after) = after
+                .split_once(']')
+                .ok_or_else(|| format!("unclosed chord in \"{}\"", text))?;
+            tokens.push(Token::Chord(symbol.trim()));
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('|') {
+            tokens.push(Token::Bar);
+            rest = after;
+        } else {
+            let end = rest.find(['[', '|']).unwrap_or(rest.len());
+            tokens.push(Token::Lyric(&rest[..end]));
+            rest = &rest[end..];
+        }
+    }
+    Ok(tokens)
+}
+
+fn is_no_chord(symbol: &str) -> bool {
+    matches!(symbol.to_ascii_uppercase().as_str(), "N.C." | "NC" | "N.C")
+}
+
+/// Numbers a letter chord such as "F#m7" or "D/F#" in `key`.
+fn letter_chord(symbol: &str, key: &Key) -> Result<ChordData, String> {
+    let invalid = || format!("invalid chord \"{}\"", symbol);
+    let (body, bass) = match symbol.split_once('/') {
+        Some((body, bass)) => (body, Some(bass)),
+        None => (symbol, None),
+    };
+
+    let (root, suffix) = split_note(body).ok_or_else(invalid)?;
+    let (quality, extension) =
+        parse_suffix(suffix).map_err(|reason| format!("{}: {}", invalid(), reason))?;
+    let (degree, accidental) = key.degree_of(&root);
+
+    let bass = match bass {
+        Some(bass) => match split_note(bass) {
+            Some((note, "")) => {
+                let (degree, accidental) = key.degree_of(&note);
+                Some(Bass { degree, accidental })
+            }
+            _ => return Err(invalid()),
+        },
+        None => None,
+    };
+
+    Ok(ChordData {
+        degree,
+        // Letter chords spell out their quality; a bare letter is major
+        quality: quality.unwrap_or(ChordQuality::Major).name().to_string(),
+        accidental,
+        bass,
+        extension,
+        push: false,
+    })
+}
+
+/// Splits a note name ("Bb", "F#") off the front of a chord symbol.
+fn split_note(text: &str) -> Option<(Note, &str)> {
+    let first = text.chars().next()?;
+    if !first.is_ascii_alphabetic() || !"ABCDEFG".contains(first.to_ascii_uppercase()) {
+        return None;
+    }
+    let end = text[1..]
+        .find(|c| c != 'b' && c != '#')
+        .map_or(text.len(), |index| index + 1);
+    Some((text[..end].parse().ok()?, &text[end..]))
+}
+
+/// Exports a chart as a ChordPro song in `key`, given with its mode as from
+/// `Chart::sounding_key`, or, when there is no key, with number chords such
+/// as `[4]` and `[6m]`. ChordPro keys are major or minor, so other modes go
+/// in a `{meta: mode dorian}` directive. Pushed chords are listed in a
+/// `{comment}` after their line.
+///
+/// Sections named like a chorus or bridge become `{start_of_chorus}` and
+/// `{start_of_bridge}`; every other named section is a labelled
+/// `{start_of_verse}`. A repeat of the last chorus is written as `{chorus}`,
+/// other repeated sections in full. Repeat signs, rehearsal marks and notes
+/// become `{comment}` lines. Lyrics follow the chords they are sung over, and
+/// lines with more than one chord in a bar mark every bar with `|`.
+pub fn export(chart: &Chart, key: Option<&Key>) -> String {
+    let mut out = String::new();
+    if let Some(title) = &chart.title {
+        out.push_str(&format!("{{title: {}}}\n", title));
+    }
+    if let Some(key) = key {
+        let minor = key.mode.diatonic_quality(1, None) != ChordQuality::Major;
+        let suffix = if minor { "m" } else { "" };
+        out.push_str(&format!("{{key: {}{}}}\n", key.tonic, suffix));
+        if !matches!(key.mode, Mode::Major | Mode::Minor) {
+            out.push_str(&format!("{{meta: mode {}}}\n", key.mode));
+        }
+    }
+    // Relatively numbered minor charts count from the relative major
+    let letter_key = key.map(|key| key.numbering_key(chart.minor_numbering));
+    if !chart.time.is_common_time() {
+        out.push_str(&format!("{{time: {}}}\n", chart.time));
+    }
+    if let Some(tempo) = chart.tempo {
+        out.push_str(&format!("{{tempo: {}}}\n", tempo));
+    }
+    if let Some(capo) = chart.capo {
+        out.push_str(&format!("{{capo: {}}}\n", capo));
+    }
+
+    let mut last_chorus: Option<&str> = None;
+    for section in &chart.sections {
+        out.push('\n');
+        if section.is_reference()
+            && section.last_measure.is_none()
+            && section.reference.as_deref() == last_chorus
+        {
+            out.push_str("{chorus}\n");
+            continue;
+        }
+
+        let environment = environment(&section.name);
+        if let Some((environment, default)) = environment {
+            if section.name == default {
+                out.push_str(&format!("{{start_of_{}}}\n", environment));
+            } else {
+                out.push_str(&format!("{{start_of_{}: {}}}\n", environment, section.name));
+            }
+            if environment == "chorus" && !section.is_reference() {
+                last_chorus = Some(&section.name);
+            }
+        }
+        for line in &section.lines {
+            export_line(&mut out, line, letter_key.as_ref());
+        }
+        if let Some((environment, _)) = environment {
+            out.push_str(&format!("{{end_of_{}}}\n", environment));
+        }
+    }
+    out
+}
+
+/// The ChordPro environment for a section name and its default label.
+fn environment(name: &str) -> Option<(&'static str, &'static str)> {
+    let lower = name.to_ascii_lowercase();
+    if name.is_empty() {
+        None
+    } else if lower.starts_with("chorus") {
+        Some(("chorus", "Chorus"))
+    } else if lower.starts_with("bridge") {
+        Some(("bridge", "Bridge"))
+    } else {
+        Some(("verse", "Verse"))
+    }
+}
+
+/// Writes one chart line as lyric lines, split wherever a repeat sign or
+/// rehearsal mark needs a comment of its own. Spacers are dropped.
+fn export_line(out: &mut String, line: &Line, key: Option<&Key>) {
+    let barred = line.line.iter().any(
+        |element| matches!(element, LineElement::Measure { measure, .. } if measure.len() > 1),
+    );
+    let mut measures: Vec<String> = Vec::new();
+    let mut notes: Vec<&str> = Vec::new();
+    let mut pushes: Vec<String> = Vec::new();
+    let mut symbol = |def: &ChordDef| {
+        let chord: Chord = def.chord.clone().into();
+        let name = match key {
+            Some(key) => key.chord_name(&chord),
+            None => chord.to_string(),
+        };
+        if chord.push {
+            pushes.push(name.clone());
+        }
+        name
+    };
+
+    for (index, element) in line.line.iter().enumerate() {
+        let comment = match element {
+            LineElement::Measure { measure, .. } => {
+                let lyric = line.line[index + 1..]
+                    .iter()
+                    .map_while(|element| match element {
+                        LineElement::Annotation { annotation } => Some(annotation),
+                        _ => None,
+                    })
+                    .find(|annotation| matches!(annotation.kind, AnnotationKind::Lyric));
+                let lyric_at = lyric
+                    .and_then(|lyric| lyric.chord)
+                    .unwrap_or(1)
+                    .clamp(1, measure.len().max(1));
+                let mut text = String::new();
+                for (position, def) in measure.iter().enumerate() {
+                    text.push_str(&format!("[{}]", symbol(def)));
+                    match lyric {
+                        Some(lyric) if position + 1 == lyric_at => text.push_str(&lyric.text),
+                        _ => {}
+                    }
+                    text.push(' ');
+                }
+                measures.push(text.trim_end().to_string());
+                None
+            }
+            LineElement::Annotation { annotation } => {
+                if matches!(annotation.kind, AnnotationKind::Note) {
+                    notes.push(&annotation.text);
+                }
+                None
+            }
+            LineElement::Repeat {
+                repeat: RepeatSign::Begin,
+            } => Some("Begin repeat".to_string()),
+            LineElement::Repeat {
+                repeat: RepeatSign::End,
+            } => Some("End repeat".to_string()),
+            LineElement::Rehearsal { mark } => Some(format!("Rehearsal {}", mark)),
+            LineElement::Spacer => None,
+        };
+        if let Some(comment) = comment {
+            push_measures(out, &mut measures, barred);
+            out.push_str(&format!("{{comment: {}}}\n", comment));
+        }
+    }
+    push_measures(out, &mut measures, barred);
+    if !pushes.is_empty() {
+        out.push_str(&format!("{{comment: Push {}}}\n", pushes.join(", ")));
+    }
+    for note in notes {
+        out.push_str(&format!("{{comment: {}}}\n", note));
+    }
+}
+
+fn push_measures(out: &mut String, measures: &mut Vec<String>, barred: bool) {
+    if measures.is_empty() {
+        return;
+    }
+    if barred {
+        out.push_str(&format!("| {} |\n", measures.join(" | ")));
+    } else {
+        out.push_str(&measures.join(" "));
+        out.push('\n');
+    }
+    measures.clear();
+}
@@ -0,0 +1,95 @@
+use crate::chart::Chart;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormEntry {
+    pub label: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Form {
+    pub entries: Vec<FormEntry>,
+}
+
+impl Form {
+    /// Builds the form from the chart's sections, collapsing back-to-back
+    /// sections with the same label into a single counted entry.
+    /// Unnamed sections (e.g. a bare line file) are not part of the form.
+    pub fn from_chart(chart: &Chart) -> Self {
+        let mut entries: Vec<FormEntry> = Vec::new();
+
+        for section in &chart.sections {
+            let label = section_abbreviation(&section.name);
+            if label.is_empty() {
+                continue;
+            }
+            match entries.last_mut() {
+                Some(last) if last.label == label => last.count += 1,
+                _ => entries.push(FormEntry { label, count: 1 }),
+            }
+        }
+
+        Self { entries }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The form as it is called out loud, with every section spelled out.
+    pub fn expanded(&self) -> String {
+        self.entries
+            .iter()
+            .flat_map(|entry| std::iter::repeat_n(entry.label.as_str(), entry.count))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl fmt::Display for Form {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                if entry.count > 1 {
+                    format!("{}x{}", entry.label, entry.count)
+                } else {
+                    entry.label.clone()
+                }
+            })
+            .collect();
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+/// Maps a section name to its road-map abbreviation. Numbering and
+/// punctuation are ignored, so "Verse 2" and "Pre-Chorus" map to "V" and
+/// "PC". Unknown names fall back to their upper-cased first letter.
+pub fn section_abbreviation(name: &str) -> String {
+    let normalized: String = name
+        .chars()
+        .filter(|c| c.is_ascii_alphabetic())
+        .collect::<String>()
+        .to_lowercase();
+
+    let label = match normalized.as_str() {
+        "" => return String::new(),
+        "intro" => "I",
+        "verse" => "V",
+        "prechorus" => "PC",
+        "chorus" => "C",
+        "bridge" => "B",
+        "breakdown" => "Bd",
+        "instrumental" => "Inst",
+        "solo" => "S",
+        "interlude" => "Int",
+        "turnaround" => "TA",
+        "tag" => "T",
+        "outro" => "O",
+        "ending" => "E",
+        _ => return normalized[..1].to_uppercase(),
+    };
+    label.to_string()
+}
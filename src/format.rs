@@ -0,0 +1,125 @@
+use crate::chart::Chart;
+use crate::line::Line;
+use crate::parser::{ChartParser, ShorthandParser};
+use crate::shorthand;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+/// Canonical YAML for a chart: fixed key order and indentation, chord
+/// qualities only where the chart has them, and `ref` sections written as
+/// references rather than as copies of the lines they repeat.
+pub fn chart_to_yaml(chart: &Chart) -> Result<String> {
+    let mut chart = chart.clone();
+    for section in &mut chart.sections {
+        if let Some(reference) = &section.reference {
+            section.lines.clear();
+            if section.name == *reference {
+                section.name.clear();
+            }
+        }
+    }
+    to_yaml(&chart)
+}
+
+/// Canonical YAML for a single line file.
+pub fn line_to_yaml(line: &Line) -> Result<String> {
+    to_yaml(line)
+}
+
+/// The canonical text of a chart file: shorthand for `.nns` files and YAML
+/// otherwise, with line files kept as line files. Files with includes or
+/// comments are refused, since formatting would inline the included files
+/// and drop the comments.
+pub fn format_file<P: AsRef<Path>>(path: P) -> Result<String> {
+    let path = path.as_ref();
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("No such file or directory: {}", path.display()))?;
+    let is_shorthand = path.extension().is_some_and(|extension| extension == "nns");
+
+    let comment = if is_shorthand {
+        shorthand_comment(&content)
+    } else {
+        yaml_comment(&content)
+    };
+    if let Some(line) = comment {
+        anyhow::bail!(
+            "Cannot format {}: it has a comment on line {}, which would be lost",
+            path.display(),
+            line
+        );
+    }
+
+    if is_shorthand {
+        return shorthand::write(&ShorthandParser::parse_str(&content)?);
+    }
+    let yaml_value: serde_yaml::Value =
+        serde_yaml::from_str(&content).with_context(|| "Failed to parse YAML: invalid format")?;
+    if has_include(&yaml_value) {
+        anyhow::bail!(
+            "Cannot format {}: it includes other files, which would be inlined",
+            path.display()
+        );
+    }
+
+    // Qualities stay as written, so chords left to the key stay that way
+    let chart = ChartParser::parse_file_as_written(path)?;
+    if yaml_value.get("line").is_some() {
+        line_to_yaml(&chart.sections[0].lines[0])
+    } else {
+        chart_to_yaml(&chart)
+    }
+}
+
+/// The first line, counted from 1, holding a `//` shorthand comment.
+fn shorthand_comment(text: &str) -> Option<usize> {
+    text.lines()
+        .position(|line| line.trim_start().starts_with("//"))
+        .map(|index| index + 1)
+}
+
+/// The first line, counted from 1, holding a YAML comment: a `#` outside
+/// quotes at the start of the line or after whitespace.
+fn yaml_comment(text: &str) -> Option<usize> {
+    let has_comment = |line: &str| {
+        let mut quote = None;
+        let mut previous = ' ';
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (quote, c) {
+                (None, '#') if previous.is_whitespace() => return true,
+                // Quotes only open a scalar, so "don't" stays plain
+                (None, '"' | '\'') if previous.is_whitespace() || "[{,".contains(previous) => {
+                    quote = Some(c)
+                }
+                (Some('"'), '\\') => {
+                    chars.next();
+                }
+                (Some('\''), '\'') if chars.peek() == Some(&'\'') => {
+                    chars.next();
+                }
+                (Some(open), _) if c == open => quote = None,
+                _ => {}
+            }
+            previous = c;
+        }
+        false
+    };
+    text.lines().position(has_comment).map(|index| index + 1)
+}
+
+fn to_yaml<T: Serialize>(value: &T) -> Result<String> {
+    let yaml = serde_yaml::to_string(value).context("Failed to write YAML")?;
+    Ok(format!("---\n{}", yaml))
+}
+
+fn has_include(value: &serde_yaml::Value) -> bool {
+    match value {
+        serde_yaml::Value::Mapping(map) => {
+            map.contains_key("include") || map.values().any(has_include)
+        }
+        serde_yaml::Value::Sequence(items) => items.iter().any(has_include),
+        _ => false,
+    }
+}
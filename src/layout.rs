@@ -0,0 +1,452 @@
+//! The layout stage shared by every output: charts are turned into glyphs
+//! positioned in SVG units, which a `RenderBackend` then draws.
+
+use crate::chart::{Chart, Section, TimeSignature};
+use crate::chord::{Accidental, Chord, ChordQuality};
+use crate::form::Form;
+use crate::line::{Annotation, AnnotationKind, Line, LineElement, RepeatSign};
+use crate::measure::Measure;
+use crate::numbering::MeasureNumbers;
+use crate::renderer::{
+    CapoDisplay, MeasureNumbering, NotationType, CHORD_SPACING, LINE_HEIGHT, SECTION_LABEL_HEIGHT,
+};
+use crate::theory::{solfege, Key};
+
+/// Something to draw, anchored at a point in SVG units from the top-left.
+#[derive(Debug, Clone)]
+pub struct Glyph {
+    pub x: i32,
+    pub y: i32,
+    pub kind: GlyphKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum GlyphKind {
+    /// A white background filling the canvas.
+    Background,
+    /// The start of a measure, at its first chord; it marks bar boundaries
+    /// for backends that draw barlines.
+    Measure {
+        chords: usize,
+        spacing: i32,
+    },
+    /// A chord centered on its anchor, with the capo shape to show beneath
+    /// it on capo charts.
+    Chord {
+        label: String,
+        shape: Option<String>,
+    },
+    /// A bar number for the measure whose first chord is at the anchor.
+    MeasureNumber(u32),
+    /// A rehearsal letter for the measure whose first chord is at the anchor.
+    RehearsalMark(String),
+    Repeat(RepeatSign),
+    Spacer,
+    /// A note above or lyric below the chord at the anchor, with `width`
+    /// units to the end of its measure.
+    Annotation {
+        text: String,
+        kind: AnnotationKind,
+        width: i32,
+    },
+    /// A section name, above the line whose first chord is at the anchor.
+    SectionLabel(String),
+    /// "(Chorus)" in place of a referenced section's lines.
+    Placeholder(String),
+    RoadMap(String),
+    CapoMarker(u32),
+}
+
+/// An output format that draws laid-out glyphs.
+pub trait RenderBackend {
+    fn draw(&mut self, glyph: &Glyph);
+}
+
+/// How charts are laid out: chord notation, letter names, capo shapes,
+/// references and bar numbers.
+#[derive(Clone)]
+pub struct Layout {
+    notation_type: NotationType,
+    expand_references: bool,
+    measure_numbering: MeasureNumbering,
+    key: Option<Key>,
+    shapes_key: Option<Key>,
+    capo_display: CapoDisplay,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Layout {
+    pub fn new() -> Self {
+        Self::with_notation(NotationType::Regular)
+    }
+
+    pub fn with_notation(notation_type: NotationType) -> Self {
+        Self {
+            notation_type,
+            expand_references: true,
+            measure_numbering: MeasureNumbering::Off,
+            key: None,
+            shapes_key: None,
+            capo_display: CapoDisplay::SoundingAbove,
+        }
+    }
+
+    /// Whether `ref` sections are laid out in full (the default) or as a
+    /// one-line placeholder naming the section they repeat.
+    pub fn set_expand_references(&mut self, expand: bool) -> &mut Self {
+        self.expand_references = expand;
+        self
+    }
+
+    /// Bar numbers are laid out by `chart`, which numbers the whole chart.
+    pub fn set_measure_numbering(&mut self, numbering: MeasureNumbering) -> &mut Self {
+        self.measure_numbering = numbering;
+        self
+    }
+
+    /// With a key set, chords are laid out as letter names in that key
+    /// ("Am", "Bb/D") instead of numbers.
+    pub fn set_key(&mut self, key: Option<Key>) -> &mut Self {
+        self.key = key;
+        self
+    }
+
+    /// With both a key and a capo shapes key set, each chord is shown in
+    /// both keys, e.g. "A" sounding over a "G" shape with a capo on 2.
+    pub fn set_capo_shapes(&mut self, shapes_key: Option<Key>) -> &mut Self {
+        self.shapes_key = shapes_key;
+        self
+    }
+
+    pub fn set_capo_display(&mut self, display: CapoDisplay) -> &mut Self {
+        self.capo_display = display;
+        self
+    }
+
+    pub fn key(&self) -> Option<&Key> {
+        self.key.as_ref()
+    }
+
+    pub fn expands_references(&self) -> bool {
+        self.expand_references
+    }
+
+    pub fn measure_numbering(&self) -> MeasureNumbering {
+        self.measure_numbering
+    }
+
+    pub fn chord(&self, chord: &Chord, x: i32, y: i32) -> Glyph {
+        let (label, shape) = self.chord_labels(chord);
+        Glyph {
+            x,
+            y,
+            kind: GlyphKind::Chord { label, shape },
+        }
+    }
+
+    pub fn measure(&self, measure: &Measure, x: i32, y: i32) -> Vec<Glyph> {
+        let mut glyphs = Vec::new();
+        self.spaced_measure(measure, CHORD_SPACING, x, y, &mut glyphs);
+        glyphs
+    }
+
+    fn spaced_measure(
+        &self,
+        measure: &Measure,
+        spacing: i32,
+        x: i32,
+        y: i32,
+        glyphs: &mut Vec<Glyph>,
+    ) {
+        let chords = measure.get_chords();
+        glyphs.push(Glyph {
+            x,
+            y,
+            kind: GlyphKind::Measure {
+                chords: chords.len(),
+                spacing,
+            },
+        });
+        let mut current_x = x;
+        for chord in chords {
+            glyphs.push(self.chord(chord, current_x, y));
+            current_x += spacing;
+        }
+    }
+
+    pub fn line(&self, line: &Line, x: i32, y: i32) -> Vec<Glyph> {
+        let mut glyphs = Vec::new();
+        self.numbered_line(line, &[], TimeSignature::default(), x, y, &mut glyphs);
+        glyphs
+    }
+
+    /// Lays out a line of a chart with its bar numbers, returning the x
+    /// just past its last element.
+    pub(crate) fn numbered_line(
+        &self,
+        line: &Line,
+        numbers: &[Option<u32>],
+        time: TimeSignature,
+        x: i32,
+        y: i32,
+        glyphs: &mut Vec<Glyph>,
+    ) -> i32 {
+        let mut current_x = x;
+        // Start x, chord count and chord spacing of the last measure, for annotations
+        let mut last_measure = (x, 0, CHORD_SPACING);
+        let mut numbered = false;
+
+        for (index, element) in line.line.iter().enumerate() {
+            match element {
+                LineElement::Measure { measure, pickup } => {
+                    let measure = Measure::from(measure.clone());
+                    // A pickup takes up only its share of a full bar
+                    let spacing = pickup.map_or(CHORD_SPACING, |beats| {
+                        (CHORD_SPACING * beats as i32 / time.beats as i32).max(CHORD_SPACING / 3)
+                    });
+                    self.spaced_measure(&measure, spacing, current_x, y, glyphs);
+                    if let Some(number) = numbers.get(index).copied().flatten() {
+                        if self.measure_numbering == MeasureNumbering::EveryBar || !numbered {
+                            glyphs.push(Glyph {
+                                x: current_x,
+                                y,
+                                kind: GlyphKind::MeasureNumber(number),
+                            });
+                            numbered = true;
+                        }
+                    }
+                    last_measure = (current_x, measure.get_chords().len() as i32, spacing);
+                    current_x += spacing * last_measure.1;
+                }
+                LineElement::Rehearsal { mark } => glyphs.push(Glyph {
+                    x: current_x,
+                    y,
+                    kind: GlyphKind::RehearsalMark(mark.clone()),
+                }),
+                LineElement::Repeat { repeat } => {
+                    glyphs.push(Glyph {
+                        x: current_x,
+                        y,
+                        kind: GlyphKind::Repeat(repeat.clone()),
+                    });
+                    current_x += CHORD_SPACING / 2; // Half spacing for repeats
+                }
+                LineElement::Spacer => {
+                    glyphs.push(Glyph {
+                        x: current_x,
+                        y,
+                        kind: GlyphKind::Spacer,
+                    });
+                    current_x += CHORD_SPACING / 2; // Half spacing for spacers
+                }
+                LineElement::Annotation { annotation } => {
+                    let (measure_x, chord_count, spacing) = last_measure;
+                    let offset = annotation.chord.map_or(0, |c| c as i32 - 1);
+                    let width = spacing * (chord_count - offset);
+                    glyphs.push(self.annotation(
+                        annotation,
+                        measure_x + spacing * offset,
+                        y,
+                        width,
+                    ));
+                }
+            }
+        }
+        current_x
+    }
+
+    pub fn annotation(&self, annotation: &Annotation, x: i32, y: i32, width: i32) -> Glyph {
+        Glyph {
+            x,
+            y,
+            kind: GlyphKind::Annotation {
+                text: annotation.text.clone(),
+                kind: annotation.kind,
+                width,
+            },
+        }
+    }
+
+    /// Lays out every section of a chart, one line below another, with each
+    /// section's name above its first line.
+    pub fn chart(&self, chart: &Chart, x: i32, y: i32) -> Vec<Glyph> {
+        let mut glyphs = Vec::new();
+        let mut current_y = y;
+        let numbers = self.measure_numbers(chart);
+
+        for (section_index, section) in chart.sections.iter().enumerate() {
+            if !section.name.is_empty() {
+                glyphs.push(self.section_label(&section.name, x, current_y));
+            }
+            if let (Some(reference), false) = (&section.reference, self.expand_references) {
+                glyphs.extend(self.placeholder(reference, section, x, current_y));
+                current_y += LINE_HEIGHT + SECTION_LABEL_HEIGHT;
+                continue;
+            }
+            for (line_index, line) in section.lines.iter().enumerate() {
+                let line_numbers = numbers.line(section_index, line_index);
+                self.numbered_line(line, line_numbers, chart.time, x, current_y, &mut glyphs);
+                current_y += LINE_HEIGHT;
+            }
+            current_y += SECTION_LABEL_HEIGHT;
+        }
+        glyphs
+    }
+
+    /// The chart's bar numbers, or none when they are turned off.
+    pub(crate) fn measure_numbers(&self, chart: &Chart) -> MeasureNumbers {
+        match self.measure_numbering {
+            MeasureNumbering::Off => MeasureNumbers::default(),
+            _ => chart.measure_numbers(),
+        }
+    }
+
+    pub fn section_label(&self, name: &str, x: i32, y: i32) -> Glyph {
+        Glyph {
+            x,
+            y,
+            kind: GlyphKind::SectionLabel(name.to_string()),
+        }
+    }
+
+    /// "(Chorus)" in place of a referenced section's lines, followed by its
+    /// replacement last measure if it overrides one.
+    pub fn placeholder(&self, reference: &str, section: &Section, x: i32, y: i32) -> Vec<Glyph> {
+        let mut glyphs = vec![Glyph {
+            x,
+            y,
+            kind: GlyphKind::Placeholder(reference.to_string()),
+        }];
+        if let Some(last_measure) = &section.last_measure {
+            let measure = Measure::from(last_measure.clone());
+            glyphs.extend(self.measure(&measure, x + 2 * CHORD_SPACING, y));
+        }
+        glyphs
+    }
+
+    /// The form as a one-line road map, e.g. "I V C V C B Cx2 O".
+    pub fn road_map(&self, form: &Form, x: i32, y: i32) -> Glyph {
+        Glyph {
+            x,
+            y,
+            kind: GlyphKind::RoadMap(form.to_string()),
+        }
+    }
+
+    /// A "Capo 3" marker, right-aligned at `x`.
+    pub fn capo_marker(&self, capo: u32, x: i32, y: i32) -> Glyph {
+        Glyph {
+            x,
+            y,
+            kind: GlyphKind::CapoMarker(capo),
+        }
+    }
+
+    /// The quality symbol and extension written after a chord's root.
+    fn quality_to_string(&self, chord: &Chord) -> String {
+        let quality = match (&chord.quality, self.notation_type) {
+            (ChordQuality::Major, _) => "",
+            (ChordQuality::Minor, NotationType::Regular | NotationType::Solfege) => "m",
+            (ChordQuality::Minor, NotationType::Compact) => "-",
+            // Roman numerals show minor by their case
+            (ChordQuality::Minor, NotationType::Roman) => "",
+            (ChordQuality::Sus2, _) => "sus2",
+            (ChordQuality::Sus4, _) => "sus4",
+            (ChordQuality::Aug, NotationType::Regular | NotationType::Solfege) => "aug",
+            (ChordQuality::Aug, NotationType::Compact | NotationType::Roman) => "+",
+            (ChordQuality::Dim, NotationType::Regular | NotationType::Solfege) => "dim",
+            (ChordQuality::Dim, NotationType::Compact) => "º",
+            (ChordQuality::Dim, NotationType::Roman) => "°",
+        };
+        format!("{}{}", quality, chord.extension.as_deref().unwrap_or(""))
+    }
+
+    fn chord_name(&self, chord: &Chord, key: Option<&Key>) -> String {
+        let accidental = chord.accidental.as_ref().map_or("", Accidental::symbol);
+        let (root, quality) = match (key, self.notation_type) {
+            // Letter names take the usual suffixes whatever the notation
+            (Some(key), NotationType::Roman) => {
+                let extension = chord.extension.as_deref().unwrap_or("");
+                let root = key.note_name(chord.degree, chord.accidental);
+                (root, format!("{}{}", chord.quality.suffix(), extension))
+            }
+            (Some(key), _) => (
+                key.note_name(chord.degree, chord.accidental),
+                self.quality_to_string(chord),
+            ),
+            (None, NotationType::Roman) => {
+                let numeral = roman_numeral(chord.degree);
+                let numeral = match chord.quality {
+                    ChordQuality::Minor | ChordQuality::Dim => numeral.to_lowercase(),
+                    _ => numeral,
+                };
+                (
+                    format!("{}{}", accidental, numeral),
+                    self.quality_to_string(chord),
+                )
+            }
+            (None, NotationType::Solfege) => (
+                solfege(chord.degree, chord.accidental).to_string(),
+                self.quality_to_string(chord),
+            ),
+            (None, _) => (
+                format!("{}{}", accidental, chord.degree),
+                self.quality_to_string(chord),
+            ),
+        };
+
+        // Pushes are marked ahead of the chord, as in "<4"
+        let root = if chord.push {
+            format!("<{}", root)
+        } else {
+            root
+        };
+        match (&chord.bass, key) {
+            (Some(bass), Some(key)) => format!("{}{}/{}", root, quality, key.bass_name(bass)),
+            (Some(bass), None) => match self.notation_type {
+                NotationType::Solfege => format!(
+                    "{}{}/{}",
+                    root,
+                    quality,
+                    solfege(bass.degree, bass.accidental)
+                ),
+                NotationType::Roman => format!(
+                    "{}{}/{}{}",
+                    root,
+                    quality,
+                    bass.accidental.as_ref().map_or("", Accidental::symbol),
+                    roman_numeral(bass.degree)
+                ),
+                _ => format!("{}{}/{}", root, quality, bass),
+            },
+            (None, _) => format!("{}{}", root, quality),
+        }
+    }
+
+    /// The chord's symbol and, on capo charts, the other set of letter names
+    /// shown smaller underneath.
+    pub fn chord_labels(&self, chord: &Chord) -> (String, Option<String>) {
+        let (Some(sounding), Some(shapes)) = (&self.key, &self.shapes_key) else {
+            return (self.chord_name(chord, self.key.as_ref()), None);
+        };
+        let (above, below) = match self.capo_display {
+            CapoDisplay::SoundingAbove => (sounding, shapes),
+            CapoDisplay::ShapesAbove => (shapes, sounding),
+        };
+        (
+            self.chord_name(chord, Some(above)),
+            Some(self.chord_name(chord, Some(below))),
+        )
+    }
+}
+
+/// Upper-case roman numeral for a scale degree; degrees above 7 wrap.
+fn roman_numeral(degree: i32) -> String {
+    const NUMERALS: [&str; 7] = ["I", "II", "III", "IV", "V", "VI", "VII"];
+    NUMERALS[(degree - 1).rem_euclid(7) as usize].to_string()
+}
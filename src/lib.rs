@@ -1,5 +1,17 @@
+pub mod chart;
 pub mod chord;
+pub mod chordpro;
+pub mod form;
+pub mod format;
+pub mod layout;
 pub mod line;
 pub mod measure;
+pub mod midi;
+pub mod musicxml;
+pub mod numbering;
 pub mod parser;
+pub mod pdf;
 pub mod renderer;
+pub mod shorthand;
+pub mod text;
+pub mod theory;
@@ -1,3 +1,4 @@
+use crate::chord::{Accidental, Bass};
 use crate::measure::Measure;
 use serde::{Deserialize, Serialize};
 
@@ -14,12 +15,47 @@ pub enum LineElement {
     Measure {
         #[serde(rename = "content")]
         measure: Vec<ChordDef>,
+        /// Marks a pickup (anacrusis) of the given number of beats. Pickups
+        /// are not counted in measure numbering.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pickup: Option<u32>,
     },
     Repeat {
         #[serde(rename = "content")]
         repeat: RepeatSign,
     },
     Spacer,
+    /// A rehearsal letter ("A", "B", ...) marking the following measure.
+    Rehearsal {
+        #[serde(rename = "content")]
+        mark: String,
+    },
+    /// A lyric cue or note attached to the measure right before it.
+    Annotation {
+        #[serde(rename = "content")]
+        annotation: Annotation,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnnotationKind {
+    /// Rendered below the chords.
+    Lyric,
+    /// Rendered above the chords.
+    #[default]
+    Note,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Annotation {
+    pub text: String,
+    #[serde(default)]
+    pub kind: AnnotationKind,
+    /// 1-based index of the chord within the measure; the annotation spans
+    /// the whole measure when omitted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chord: Option<usize>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,10 +63,21 @@ pub struct ChordDef {
     pub chord: ChordData,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ChordData {
     pub degree: i32,
+    /// Empty until resolved when omitted; chart parsing fills in the
+    /// diatonic quality for the chart's mode.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub quality: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accidental: Option<Accidental>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bass: Option<Bass>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extension: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub push: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,6 +85,12 @@ pub struct Line {
     pub line: Vec<LineElement>,
 }
 
+impl Default for Line {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Line {
     pub fn new() -> Self {
         Self { line: Vec::new() }
@@ -67,6 +120,10 @@ impl From<ChordData> for crate::chord::Chord {
         Self {
             degree: data.degree,
             quality,
+            accidental: data.accidental,
+            bass: data.bass,
+            extension: data.extension,
+            push: data.push,
         }
     }
 }
@@ -1,37 +1,308 @@
-use anyhow::Result;
-use clap::Parser;
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
 use nns_chart_parser::{
-    parser::LineParser,
-    renderer::{ChordRenderer, NotationType},
+    chordpro,
+    format::format_file,
+    midi,
+    musicxml::{self, Staff},
+    parser::{ChartParser, ChordProParser, ShorthandParser},
+    pdf::{self, PaperSize},
+    renderer::{
+        CapoDisplay, ChordRenderer, MeasureNumbering, NotationType, CHORD_SPACING, DEFAULT_DPI,
+        DEFAULT_MARGIN,
+    },
+    text::{TextRenderer, DEFAULT_COLUMN_WIDTH},
+    theory::Key,
 };
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
-    /// Path to the input YAML file
-    input_file: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Path to the input YAML file, a shorthand chart ending in .nns or a
+    /// ChordPro song ending in .cho, .chopro or .chordpro
+    #[arg(required = true)]
+    input_file: Option<String>,
 
     /// Use compact notation
     #[arg(long)]
     compact: bool,
+
+    /// Use roman numeral notation (I vi IV V)
+    #[arg(long, conflicts_with = "compact")]
+    roman: bool,
+
+    /// Use movable-do solfège notation (do la fa sol)
+    #[arg(long, conflicts_with_all = ["compact", "roman"])]
+    solfege: bool,
+
+    /// Print the chart's form (e.g. "I V C V C B Cx2 O") instead of rendering
+    #[arg(long)]
+    form: bool,
+
+    /// Render the form as a road map at the top of the chart
+    #[arg(long)]
+    road_map: bool,
+
+    /// Draw referenced sections as "(Chorus)" placeholders instead of in full
+    #[arg(long)]
+    placeholders: bool,
+
+    /// Render letter-name chords in this key (e.g. G, Bb, F#) instead of numbers
+    #[arg(long)]
+    key: Option<Key>,
+
+    /// Render letter-name chords in the key declared by the chart
+    #[arg(long, conflicts_with = "key")]
+    letters: bool,
+
+    /// Capo fret, overriding the chart's; letter-name charts then show the
+    /// capo shapes under the sounding chords
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..=11))]
+    capo: Option<u32>,
+
+    /// With a capo, draw the shapes large and the sounding chords beneath
+    #[arg(long)]
+    shapes_above: bool,
+
+    /// Show bar numbers at the start of each line or on every bar
+    #[arg(long, value_enum)]
+    measure_numbers: Option<MeasureNumbersArg>,
+
+    /// Output format; the output is written next to the input file
+    #[arg(long, value_enum, default_value = "svg")]
+    to: OutputFormat,
+
+    /// With --to musicxml, write rests under the chords instead of slashes
+    #[arg(long)]
+    rests: bool,
+
+    /// With --to midi, add a bass track playing the chord roots
+    #[arg(long)]
+    bass: bool,
+
+    /// Resolution of PNG, JPEG and WebP output; 96 gives one pixel per SVG unit
+    #[arg(long, default_value_t = DEFAULT_DPI)]
+    dpi: f32,
+
+    /// Space around the chart in SVG and image output, in SVG units
+    #[arg(long, default_value_t = DEFAULT_MARGIN, value_parser = clap::value_parser!(i32).range(0..))]
+    margin: i32,
+
+    /// Page size of PDF output
+    #[arg(long, value_enum, default_value = "letter")]
+    paper: PaperArg,
+
+    /// Columns given to each chord in text output
+    #[arg(long, default_value_t = DEFAULT_COLUMN_WIDTH)]
+    column_width: usize,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Rewrite chart files in canonical form
+    Fmt {
+        /// Chart files to format in place
+        #[arg(required = true)]
+        files: Vec<String>,
+
+        /// Report files that are not formatted instead of rewriting them
+        #[arg(long)]
+        check: bool,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    Svg,
+    Png,
+    Jpeg,
+    Webp,
+    /// A ChordPro song, with letter chords given --key or --letters
+    Chordpro,
+    /// A MusicXML score of chord symbols in the chart's key or --key
+    Musicxml,
+    /// A MIDI backing track of block chords in the chart's key or --key
+    Midi,
+    /// A printable PDF on Letter or A4 pages, chosen with --paper
+    Pdf,
+    /// Plain text in a monospace layout, for terminals and email
+    Text,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum PaperArg {
+    Letter,
+    A4,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum MeasureNumbersArg {
+    LineStarts,
+    EveryBar,
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
 
-    let line = LineParser::parse_file(&args.input_file)?;
+    if let Some(Command::Fmt { files, check }) = &args.command {
+        return fmt(files, *check);
+    }
+    let input_file = args.input_file.as_deref().unwrap_or_default();
+
+    let mut chart = if input_file.ends_with(".nns") {
+        ShorthandParser::parse_file(input_file)?
+    } else if [".cho", ".chopro", ".chordpro"]
+        .iter()
+        .any(|extension| input_file.ends_with(extension))
+    {
+        ChordProParser::parse_file(input_file)?
+    } else {
+        ChartParser::parse_file(input_file)?
+    };
+    if args.capo.is_some() {
+        chart.capo = args.capo;
+    }
+
+    if args.form {
+        println!("{}", chart.form());
+        return Ok(());
+    }
 
     let notation_type = if args.compact {
         NotationType::Compact
+    } else if args.roman {
+        NotationType::Roman
+    } else if args.solfege {
+        NotationType::Solfege
     } else {
         NotationType::Regular
     };
 
+    let measure_numbering = match args.measure_numbers {
+        Some(MeasureNumbersArg::LineStarts) => MeasureNumbering::LineStarts,
+        Some(MeasureNumbersArg::EveryBar) => MeasureNumbering::EveryBar,
+        None => MeasureNumbering::Off,
+    };
+
+    if args.letters && chart.key.is_none() {
+        anyhow::bail!("--letters needs a chart with a key; use --key instead");
+    }
+    let (key, shapes_key) = if args.letters || args.key.is_some() {
+        (chart.letter_key(args.key), chart.shapes_key(args.key))
+    } else {
+        (None, None)
+    };
+
+    if let OutputFormat::Chordpro = args.to {
+        let output_path = format!("{}.cho", input_file);
+        let key = key.and(chart.sounding_key(args.key));
+        std::fs::write(&output_path, chordpro::export(&chart, key.as_ref()))
+            .with_context(|| format!("Failed to write {}", output_path))?;
+        return Ok(());
+    }
+    if let OutputFormat::Musicxml = args.to {
+        let key = chart
+            .letter_key(args.key)
+            .context("MusicXML export needs a key; give the chart one or use --key")?;
+        let staff = if args.rests {
+            Staff::Rests
+        } else {
+            Staff::Slashes
+        };
+        let output_path = format!("{}.musicxml", input_file);
+        std::fs::write(&output_path, musicxml::write(&chart, &key, staff))
+            .with_context(|| format!("Failed to write {}", output_path))?;
+        return Ok(());
+    }
+    if let OutputFormat::Midi = args.to {
+        let key = chart
+            .letter_key(args.key)
+            .context("MIDI export needs a key; give the chart one or use --key")?;
+        let output_path = format!("{}.mid", input_file);
+        std::fs::write(&output_path, midi::write(&chart, &key, args.bass)?)
+            .with_context(|| format!("Failed to write {}", output_path))?;
+        return Ok(());
+    }
+
+    let capo_display = if args.shapes_above {
+        CapoDisplay::ShapesAbove
+    } else {
+        CapoDisplay::SoundingAbove
+    };
+
     let mut renderer = ChordRenderer::with_notation(notation_type);
-    renderer.init_background().render_line(&line, 100, 200);
+    renderer
+        .set_key(key)
+        .set_capo_shapes(shapes_key)
+        .set_capo_display(capo_display)
+        .set_expand_references(!args.placeholders)
+        .set_measure_numbering(measure_numbering)
+        .set_margin(args.margin)
+        .init_background();
+
+    if let OutputFormat::Pdf = args.to {
+        let paper = match args.paper {
+            PaperArg::Letter => PaperSize::Letter,
+            PaperArg::A4 => PaperSize::A4,
+        };
+        let output_path = format!("{}.pdf", input_file);
+        std::fs::write(&output_path, pdf::write(&chart, renderer.layout(), paper)?)
+            .with_context(|| format!("Failed to write {}", output_path))?;
+        return Ok(());
+    }
+    if let OutputFormat::Text = args.to {
+        let mut text = TextRenderer::new(renderer.layout());
+        text.set_column_width(args.column_width);
+        let output_path = format!("{}.txt", input_file);
+        std::fs::write(&output_path, text.render_chart(&chart))
+            .with_context(|| format!("Failed to write {}", output_path))?;
+        return Ok(());
+    }
+    if args.road_map {
+        renderer.render_road_map(&chart.form(), 100 - CHORD_SPACING / 2, 30);
+    }
+    // The canvas fits the content, so this only leaves room for the road map
+    // and capo marker above the first section
+    renderer.render_chart(&chart, 100, 120);
+    if let (Some(capo), Some(_)) = (chart.capo, shapes_key) {
+        // Right-aligned with the widest line
+        let right = renderer.bounds().map_or(100, |bounds| bounds.right());
+        renderer.render_capo_marker(capo, right, 30);
+    }
+
+    match args.to {
+        OutputFormat::Png => renderer.save_image(&format!("{}.png", input_file), args.dpi)?,
+        OutputFormat::Jpeg => renderer.save_image(&format!("{}.jpg", input_file), args.dpi)?,
+        OutputFormat::Webp => renderer.save_image(&format!("{}.webp", input_file), args.dpi)?,
+        _ => renderer.save(&format!("{}.svg", input_file))?,
+    }
+
+    Ok(())
+}
 
-    let output_path = format!("{}.svg", args.input_file);
-    renderer.save(&output_path)?;
+fn fmt(files: &[String], check: bool) -> Result<()> {
+    let mut unformatted = 0;
+    for file in files {
+        let formatted = format_file(file)?;
+        let current = std::fs::read_to_string(file)
+            .with_context(|| format!("No such file or directory: {}", file))?;
+        if formatted == current {
+            continue;
+        }
+        if check {
+            println!("{} is not formatted", file);
+            unformatted += 1;
+        } else {
+            std::fs::write(file, formatted).with_context(|| format!("Failed to write {}", file))?;
+        }
+    }
 
+    if unformatted > 0 {
+        anyhow::bail!("{} file(s) need formatting", unformatted);
+    }
     Ok(())
 }
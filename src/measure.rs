@@ -17,6 +17,12 @@ pub struct MeasureCollection {
     pub measures: Vec<Measure>,
 }
 
+impl Default for Measure {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Measure {
     pub fn new() -> Self {
         Self { chords: Vec::new() }
@@ -44,6 +50,12 @@ impl Measure {
     }
 }
 
+impl Default for MeasureCollection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl MeasureCollection {
     pub fn new() -> Self {
         Self {
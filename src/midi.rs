@@ -0,0 +1,223 @@
+//! Standard MIDI File export of a chart as block chords, for practice
+//! backing tracks and click-track prep.
+
+use crate::chart::Chart;
+use crate::chord::{Chord, ChordQuality};
+use crate::line::{LineElement, RepeatSign};
+use crate::theory::{Key, Mode};
+use anyhow::Result;
+
+/// Ticks per quarter note.
+pub const TICKS_PER_QUARTER: u32 = 480;
+
+/// Tempo used when the chart does not give one.
+pub const DEFAULT_TEMPO: u32 = 120;
+
+const CHORD_CHANNEL: u8 = 0;
+const BASS_CHANNEL: u8 = 1;
+const PIANO: u8 = 0;
+const ACOUSTIC_BASS: u8 = 32;
+const CHORD_VELOCITY: u8 = 80;
+const BASS_VELOCITY: u8 = 96;
+/// Chord roots sit between C3 and B3, bass notes an octave lower.
+const CHORD_OCTAVE: i32 = 48;
+const BASS_OCTAVE: i32 = 36;
+
+/// Renders a chart in `key` as a format 1 MIDI file: a tempo track, a
+/// track of block chords and, with `bass`, a track of root notes (the slash
+/// bass where there is one).
+///
+/// Chords share their bar equally and sections play in order, with every
+/// `||: ... :||` played twice. A `:||` without a `||:` repeats from the
+/// start of its section or the previous repeat. Spacers and pushes are not
+/// played.
+///
+/// Time signatures and tempos that MIDI cannot store, such as more than 255
+/// beats to the bar, are reported as errors.
+pub fn write(chart: &Chart, key: &Key, bass: bool) -> Result<Vec<u8>> {
+    let time = chart.time;
+    if !time.unit.is_power_of_two() {
+        anyhow::bail!("Invalid time signature for MIDI: {}", time);
+    }
+    let beats = u8::try_from(time.beats)
+        .map_err(|_| anyhow::anyhow!("MIDI allows at most 255 beats to the bar, got: {}", time))?;
+    let bar_length = time.beats * TICKS_PER_QUARTER * 4 / time.unit;
+    let tempo = chart.tempo.unwrap_or(DEFAULT_TEMPO);
+
+    let mut conductor = Track::default();
+    if let Some(title) = &chart.title {
+        conductor.meta(0x03, title.as_bytes());
+    }
+    // Microseconds per quarter note, from beats of the time signature's
+    // unit, in the three bytes of the tempo event
+    let quarter = (60_000_000 * u64::from(time.unit))
+        .checked_div(4 * u64::from(tempo))
+        .filter(|quarter| (1..=0xFF_FFFF).contains(quarter))
+        .ok_or_else(|| anyhow::anyhow!("Tempo {} in {} is out of MIDI's range", tempo, time))?;
+    conductor.meta(0x51, &quarter.to_be_bytes()[5..]);
+    conductor.meta(0x58, &[beats, time.unit.trailing_zeros() as u8, 24, 8]);
+    let minor = u8::from(key.mode == Mode::Minor);
+    conductor.meta(0x59, &[key.signature().clamp(-7, 7) as i8 as u8, minor]);
+
+    let mut chords = Track::default();
+    chords.meta(0x03, b"Chords");
+    chords.event(&[0xC0 | CHORD_CHANNEL, PIANO]);
+    let mut roots = Track::default();
+    roots.meta(0x03, b"Bass");
+    roots.event(&[0xC0 | BASS_CHANNEL, ACOUSTIC_BASS]);
+
+    for (bar, pickup) in playback_order(chart) {
+        let length = match pickup {
+            Some(beats) => beats * TICKS_PER_QUARTER * 4 / chart.time.unit,
+            None => bar_length,
+        };
+        if bar.is_empty() {
+            chords.rest(length);
+            roots.rest(length);
+        }
+        let count = bar.len() as u32;
+        for (position, chord) in bar.iter().enumerate() {
+            // Earlier chords take any ticks that do not split evenly
+            let duration = length / count + u32::from((position as u32) < length % count);
+
+            let notes = voicing(chord, key);
+            chords.notes(CHORD_CHANNEL, &notes, CHORD_VELOCITY, duration);
+            let root = match &chord.bass {
+                Some(bass) => key.spell(bass.degree, bass.accidental),
+                None => key.spell(chord.degree, chord.accidental),
+            };
+            let root = BASS_OCTAVE + root.pitch_class();
+            roots.notes(BASS_CHANNEL, &[root as u8], BASS_VELOCITY, duration);
+        }
+    }
+
+    let mut tracks = vec![conductor, chords];
+    if bass {
+        tracks.push(roots);
+    }
+
+    let mut out = Vec::new();
+    out.extend_from_slice(b"MThd");
+    out.extend_from_slice(&6u32.to_be_bytes());
+    out.extend_from_slice(&1u16.to_be_bytes());
+    out.extend_from_slice(&(tracks.len() as u16).to_be_bytes());
+    out.extend_from_slice(&(TICKS_PER_QUARTER as u16).to_be_bytes());
+    for mut track in tracks {
+        track.meta(0x2F, &[]);
+        out.extend_from_slice(b"MTrk");
+        out.extend_from_slice(&(track.data.len() as u32).to_be_bytes());
+        out.extend_from_slice(&track.data);
+    }
+    Ok(out)
+}
+
+/// Every bar of the chart in the order it is played, with its pickup
+/// length if it is a pickup.
+fn playback_order(chart: &Chart) -> Vec<(Vec<Chord>, Option<u32>)> {
+    let mut bars = Vec::new();
+    for section in &chart.sections {
+        let mut repeat_from = bars.len();
+        for element in section.lines.iter().flat_map(|line| &line.line) {
+            match element {
+                LineElement::Measure { measure, pickup } => bars.push((
+                    measure.iter().map(|def| def.chord.clone().into()).collect(),
+                    *pickup,
+                )),
+                LineElement::Repeat {
+                    repeat: RepeatSign::Begin,
+                } => repeat_from = bars.len(),
+                LineElement::Repeat {
+                    repeat: RepeatSign::End,
+                } => {
+                    bars.extend_from_within(repeat_from..);
+                    repeat_from = bars.len();
+                }
+                _ => {}
+            }
+        }
+    }
+    bars
+}
+
+/// MIDI note numbers for a chord: the triad in close position from a root
+/// between C3 and B3, plus any sixth, seventh or ninth its extension names.
+fn voicing(chord: &Chord, key: &Key) -> Vec<u8> {
+    let tones = key.chord_tones(chord);
+    let root = CHORD_OCTAVE + tones[0].pitch_class();
+    let mut notes = vec![root];
+    for tone in &tones[1..] {
+        let previous = *notes.last().unwrap();
+        notes.push(previous + (tone.pitch_class() - previous).rem_euclid(12));
+    }
+
+    let extension = chord.extension.as_deref().unwrap_or("");
+    let number = extension
+        .trim_start_matches("maj")
+        .trim_start_matches("add");
+    let seventh = if extension.starts_with("maj") {
+        11
+    } else if chord.quality == ChordQuality::Dim {
+        9
+    } else {
+        10
+    };
+    let digits = number
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(number.len());
+    match (extension.starts_with("add"), &number[..digits]) {
+        (false, "6") => notes.push(root + 9),
+        (false, "7") => notes.push(root + seventh),
+        (false, "9" | "11" | "13") => notes.extend([root + seventh, root + 14]),
+        (true, "9") => notes.push(root + 14),
+        _ => {}
+    }
+    notes.into_iter().map(|note| note as u8).collect()
+}
+
+/// A track chunk's events, each written with the time since the last.
+#[derive(Default)]
+struct Track {
+    data: Vec<u8>,
+    /// Ticks since the last event.
+    pending: u32,
+}
+
+impl Track {
+    /// Writes a variable-length quantity.
+    fn varlen(&mut self, value: u32) {
+        let mut bytes = vec![(value & 0x7F) as u8];
+        let mut rest = value >> 7;
+        while rest > 0 {
+            bytes.push((rest & 0x7F) as u8 | 0x80);
+            rest >>= 7;
+        }
+        self.data.extend(bytes.iter().rev());
+    }
+
+    fn event(&mut self, bytes: &[u8]) {
+        let delta = std::mem::take(&mut self.pending);
+        self.varlen(delta);
+        self.data.extend_from_slice(bytes);
+    }
+
+    fn meta(&mut self, kind: u8, bytes: &[u8]) {
+        self.event(&[0xFF, kind]);
+        self.varlen(bytes.len() as u32);
+        self.data.extend_from_slice(bytes);
+    }
+
+    fn rest(&mut self, duration: u32) {
+        self.pending += duration;
+    }
+
+    /// Sounds `notes` together for `duration` ticks.
+    fn notes(&mut self, channel: u8, notes: &[u8], velocity: u8, duration: u32) {
+        for &note in notes {
+            self.event(&[0x90 | channel, note, velocity]);
+        }
+        self.rest(duration);
+        for &note in notes {
+            self.event(&[0x80 | channel, note, 0]);
+        }
+    }
+}
@@ -0,0 +1,376 @@
+//! MusicXML export: a single part of chord symbols over slashes or rests,
+//! for importing a chart into notation software.
+
+use crate::chart::Chart;
+use crate::chord::{Chord, ChordQuality};
+use crate::line::{LineElement, RepeatSign};
+use crate::theory::{Key, Note};
+
+/// Divisions of a quarter note. 24 lets a bar of any usual meter split
+/// evenly between two, three or four chords.
+const DIVISIONS: u32 = 24;
+
+/// What goes on the staff under the chord symbols.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Staff {
+    /// Rhythm slashes, one per chord.
+    #[default]
+    Slashes,
+    /// Rests, one per chord.
+    Rests,
+}
+
+/// A measure with the marks that attach to it.
+struct Bar<'a> {
+    chords: Vec<Chord>,
+    pickup: Option<u32>,
+    section: Option<&'a str>,
+    rehearsal: Option<&'a str>,
+    repeat_start: bool,
+    repeat_end: bool,
+}
+
+/// Writes a chart as a MusicXML 4.0 partwise score with letter-name chord
+/// symbols in `key`.
+///
+/// Chords become `<harmony>` elements, sharing their bar equally. Section
+/// names are written above their first bar and rehearsal marks as
+/// rehearsal directions; repeat signs become repeat barlines. Spacers,
+/// annotations and pushes are left out.
+pub fn write(chart: &Chart, key: &Key, staff: Staff) -> String {
+    let bars = collect_bars(chart);
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n");
+    out.push_str(
+        "<!DOCTYPE score-partwise PUBLIC \"-//Recordare//DTD MusicXML 4.0 Partwise//EN\" \
+         \"http://www.musicxml.org/dtds/partwise.dtd\">\n",
+    );
+    out.push_str("<score-partwise version=\"4.0\">\n");
+    if let Some(title) = &chart.title {
+        out.push_str(&format!(
+            "  <work>\n    <work-title>{}</work-title>\n  </work>\n",
+            escape(title)
+        ));
+    }
+    out.push_str("  <part-list>\n");
+    out.push_str(
+        "    <score-part id=\"P1\">\n      <part-name>Chords</part-name>\n    </score-part>\n",
+    );
+    out.push_str("  </part-list>\n");
+    out.push_str("  <part id=\"P1\">\n");
+
+    let bar_length = chart.time.beats * DIVISIONS * 4 / chart.time.unit;
+    let mut number = 0;
+    for (index, bar) in bars.iter().enumerate() {
+        match bar.pickup {
+            Some(_) if number == 0 => out.push_str("    <measure number=\"0\" implicit=\"yes\">\n"),
+            Some(_) => out.push_str(&format!(
+                "    <measure number=\"{}a\" implicit=\"yes\">\n",
+                number
+            )),
+            None => {
+                number += 1;
+                out.push_str(&format!("    <measure number=\"{}\">\n", number));
+            }
+        }
+
+        if index == 0 {
+            out.push_str("      <attributes>\n");
+            out.push_str(&format!("        <divisions>{}</divisions>\n", DIVISIONS));
+            out.push_str(&format!(
+                "        <key>\n          <fifths>{}</fifths>\n          <mode>{}</mode>\n        </key>\n",
+                key.signature(),
+                key.mode
+            ));
+            out.push_str(&format!(
+                "        <time>\n          <beats>{}</beats>\n          <beat-type>{}</beat-type>\n        </time>\n",
+                chart.time.beats, chart.time.unit
+            ));
+            out.push_str("        <clef>\n          <sign>G</sign>\n          <line>2</line>\n        </clef>\n");
+            out.push_str("      </attributes>\n");
+        }
+        if bar.repeat_start {
+            out.push_str("      <barline location=\"left\">\n        <bar-style>heavy-light</bar-style>\n        <repeat direction=\"forward\"/>\n      </barline>\n");
+        }
+        if let Some(rehearsal) = bar.rehearsal {
+            write_direction(
+                &mut out,
+                &format!("<rehearsal>{}</rehearsal>", escape(rehearsal)),
+            );
+        }
+        if let Some(section) = bar.section {
+            write_direction(&mut out, &format!("<words>{}</words>", escape(section)));
+        }
+
+        let length = match bar.pickup {
+            Some(beats) => beats * DIVISIONS * 4 / chart.time.unit,
+            None => bar_length,
+        };
+        if bar.chords.is_empty() {
+            write_note(&mut out, length, Staff::Rests);
+        }
+        let count = bar.chords.len() as u32;
+        for (position, chord) in bar.chords.iter().enumerate() {
+            write_harmony(&mut out, chord, key);
+            // Earlier chords take any divisions that do not split evenly
+            let extra = u32::from((position as u32) < length % count);
+            write_note(&mut out, length / count + extra, staff);
+        }
+
+        if bar.repeat_end {
+            out.push_str("      <barline location=\"right\">\n        <bar-style>light-heavy</bar-style>\n        <repeat direction=\"backward\"/>\n      </barline>\n");
+        }
+        out.push_str("    </measure>\n");
+    }
+
+    out.push_str("  </part>\n");
+    out.push_str("</score-partwise>\n");
+    out
+}
+
+fn collect_bars(chart: &Chart) -> Vec<Bar<'_>> {
+    let mut bars: Vec<Bar> = Vec::new();
+    for section in &chart.sections {
+        let mut name = Some(section.name.as_str()).filter(|name| !name.is_empty());
+        let mut rehearsal = None;
+        let mut repeat_start = false;
+
+        for element in section.lines.iter().flat_map(|line| &line.line) {
+            match element {
+                LineElement::Measure { measure, pickup } => bars.push(Bar {
+                    chords: measure.iter().map(|def| def.chord.clone().into()).collect(),
+                    pickup: *pickup,
+                    section: name.take(),
+                    rehearsal: rehearsal.take(),
+                    repeat_start: std::mem::take(&mut repeat_start),
+                    repeat_end: false,
+                }),
+                LineElement::Repeat {
+                    repeat: RepeatSign::Begin,
+                } => repeat_start = true,
+                LineElement::Repeat {
+                    repeat: RepeatSign::End,
+                } => {
+                    if let Some(bar) = bars.last_mut() {
+                        bar.repeat_end = true;
+                    }
+                }
+                LineElement::Rehearsal { mark } => rehearsal = Some(mark.as_str()),
+                LineElement::Spacer | LineElement::Annotation { .. } => {}
+            }
+        }
+    }
+    bars
+}
+
+fn write_direction(out: &mut String, content: &str) {
+    out.push_str("      <direction placement=\"above\">\n");
+    out.push_str(&format!(
+        "        <direction-type>\n          {}\n        </direction-type>\n",
+        content
+    ));
+    out.push_str("      </direction>\n");
+}
+
+fn write_harmony(out: &mut String, chord: &Chord, key: &Key) {
+    let (kind, degrees) = kind(chord);
+    let text = format!(
+        "{}{}",
+        chord.quality.suffix(),
+        chord.extension.as_deref().unwrap_or("")
+    );
+
+    out.push_str("      <harmony>\n");
+    let root = key.spell(chord.degree, chord.accidental);
+    out.push_str("        <root>\n");
+    write_step(out, "root", &root);
+    out.push_str("        </root>\n");
+    out.push_str(&format!(
+        "        <kind text=\"{}\">{}</kind>\n",
+        escape(&text),
+        kind
+    ));
+    if let Some(bass) = &chord.bass {
+        out.push_str("        <bass>\n");
+        write_step(out, "bass", &key.spell(bass.degree, bass.accidental));
+        out.push_str("        </bass>\n");
+    }
+    for (value, alter, kind) in degrees {
+        out.push_str(&format!(
+            "        <degree>\n          <degree-value>{}</degree-value>\n          <degree-alter>{}</degree-alter>\n          <degree-type>{}</degree-type>\n        </degree>\n",
+            value, alter, kind
+        ));
+    }
+    out.push_str("      </harmony>\n");
+}
+
+fn write_step(out: &mut String, prefix: &str, note: &Note) {
+    out.push_str(&format!(
+        "          <{0}-step>{1}</{0}-step>\n",
+        prefix, note.letter
+    ));
+    if note.accidental != 0 {
+        out.push_str(&format!(
+            "          <{0}-alter>{1}</{0}-alter>\n",
+            prefix, note.accidental
+        ));
+    }
+}
+
+fn write_note(out: &mut String, duration: u32, staff: Staff) {
+    out.push_str("      <note>\n");
+    match staff {
+        Staff::Slashes => out.push_str(
+            "        <pitch>\n          <step>B</step>\n          <octave>4</octave>\n        </pitch>\n",
+        ),
+        Staff::Rests => out.push_str("        <rest/>\n"),
+    }
+    out.push_str(&format!("        <duration>{}</duration>\n", duration));
+    if let Some((kind, dotted)) = note_type(duration) {
+        out.push_str(&format!("        <type>{}</type>\n", kind));
+        if dotted {
+            out.push_str("        <dot/>\n");
+        }
+    }
+    if staff == Staff::Slashes {
+        out.push_str("        <stem>none</stem>\n        <notehead>slash</notehead>\n");
+    }
+    out.push_str("      </note>\n");
+}
+
+/// The note type for a duration in divisions, if it has one.
+fn note_type(duration: u32) -> Option<(&'static str, bool)> {
+    let types = [
+        ("whole", 4 * DIVISIONS),
+        ("half", 2 * DIVISIONS),
+        ("quarter", DIVISIONS),
+        ("eighth", DIVISIONS / 2),
+        ("16th", DIVISIONS / 4),
+    ];
+    types.iter().find_map(|&(kind, length)| {
+        if duration == length {
+            Some((kind, false))
+        } else if duration * 2 == length * 3 {
+            Some((kind, true))
+        } else {
+            None
+        }
+    })
+}
+
+/// A degree of a chord symbol: value, alteration and MusicXML degree type.
+type Degree = (u32, i32, &'static str);
+
+/// The MusicXML kind of a chord, with any degrees its extension adds or
+/// alters. Extensions are read as an optional "maj", a seventh, sixth or
+/// upper extension, then "add9", "b9" or "#11" style alterations.
+fn kind(chord: &Chord) -> (&'static str, Vec<Degree>) {
+    let mut rest = chord.extension.as_deref().unwrap_or("");
+    let major = rest.starts_with("maj");
+    rest = rest.trim_start_matches("maj");
+    let digits = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    let number: Option<u32> = rest[..digits].parse().ok();
+    rest = &rest[digits..];
+
+    let half_diminished = chord.quality == ChordQuality::Minor && number == Some(7);
+    let half_diminished = half_diminished && !major && rest.starts_with("b5");
+    if half_diminished {
+        rest = &rest[2..];
+    }
+
+    let kind = match (&chord.quality, number, major) {
+        _ if half_diminished => Some("half-diminished"),
+        (ChordQuality::Major, None, _) => Some("major"),
+        (ChordQuality::Major, Some(6), false) => Some("major-sixth"),
+        (ChordQuality::Major, Some(7), false) => Some("dominant"),
+        (ChordQuality::Major, Some(9), false) => Some("dominant-ninth"),
+        (ChordQuality::Major, Some(11), false) => Some("dominant-11th"),
+        (ChordQuality::Major, Some(13), false) => Some("dominant-13th"),
+        (ChordQuality::Major, Some(7), true) => Some("major-seventh"),
+        (ChordQuality::Major, Some(9), true) => Some("major-ninth"),
+        (ChordQuality::Major, Some(11), true) => Some("major-11th"),
+        (ChordQuality::Major, Some(13), true) => Some("major-13th"),
+        (ChordQuality::Minor, None, _) => Some("minor"),
+        (ChordQuality::Minor, Some(6), false) => Some("minor-sixth"),
+        (ChordQuality::Minor, Some(7), false) => Some("minor-seventh"),
+        (ChordQuality::Minor, Some(9), false) => Some("minor-ninth"),
+        (ChordQuality::Minor, Some(11), false) => Some("minor-11th"),
+        (ChordQuality::Minor, Some(13), false) => Some("minor-13th"),
+        (ChordQuality::Minor, Some(7), true) => Some("major-minor"),
+        (ChordQuality::Dim, None, _) => Some("diminished"),
+        (ChordQuality::Dim, Some(7), false) => Some("diminished-seventh"),
+        (ChordQuality::Aug, None, _) => Some("augmented"),
+        (ChordQuality::Aug, Some(7), false) => Some("augmented-seventh"),
+        (ChordQuality::Sus2, None, _) => Some("suspended-second"),
+        (ChordQuality::Sus4, None, _) => Some("suspended-fourth"),
+        _ => None,
+    };
+
+    let mut degrees = Vec::new();
+    let kind = match kind {
+        Some(kind) => kind,
+        // Anything else is the triad with the extension's degrees added
+        None => {
+            let triad = match chord.quality {
+                ChordQuality::Major => "major",
+                ChordQuality::Minor => "minor",
+                ChordQuality::Dim => "diminished",
+                ChordQuality::Aug => "augmented",
+                ChordQuality::Sus2 => "suspended-second",
+                ChordQuality::Sus4 => "suspended-fourth",
+            };
+            if let Some(number) = number {
+                if number > 7 || (number == 7 && !major) {
+                    degrees.push((7, if major { 0 } else { -1 }, "add"));
+                }
+                if number != 7 || major {
+                    degrees.push((number, 0, "add"));
+                }
+            }
+            triad
+        }
+    };
+
+    // Alterations such as "b5", "#11" and "add9"
+    while !rest.is_empty() {
+        let (alter, after) = if let Some(after) = rest.strip_prefix("add") {
+            (0, after)
+        } else if let Some(after) = rest.strip_prefix('b') {
+            (-1, after)
+        } else if let Some(after) = rest.strip_prefix('#') {
+            (1, after)
+        } else {
+            break;
+        };
+        let digits = after
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(after.len());
+        let Ok(value) = after[..digits].parse::<u32>() else {
+            break;
+        };
+        let altered = alter != 0 && (value == 5 || contains_degree(kind, value));
+        degrees.push((value, alter, if altered { "alter" } else { "add" }));
+        rest = &after[digits..];
+    }
+
+    (kind, degrees)
+}
+
+/// Whether a chord of this kind already has the given upper degree.
+fn contains_degree(kind: &str, value: u32) -> bool {
+    match value {
+        9 => kind.ends_with("ninth") || kind.ends_with("11th") || kind.ends_with("13th"),
+        11 => kind.ends_with("11th") || kind.ends_with("13th"),
+        13 => kind.ends_with("13th"),
+        _ => false,
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
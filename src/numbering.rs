@@ -0,0 +1,85 @@
+use crate::chart::Chart;
+use crate::line::LineElement;
+
+/// Bar numbers for every measure of a chart, counted across sections in
+/// written order. Bars inside repeat signs are numbered once, as in an
+/// engraved score, so "bar 33" always points at the same place on the page.
+/// Pickup measures are not numbered; the first full bar is bar 1.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MeasureNumbers {
+    /// Indexed by section, line and element; `None` for non-measure elements.
+    numbers: Vec<Vec<Vec<Option<u32>>>>,
+    rehearsal_marks: Vec<(String, u32)>,
+    total: u32,
+}
+
+impl MeasureNumbers {
+    pub fn from_chart(chart: &Chart) -> Self {
+        let mut result = Self::default();
+        let mut current = 0;
+
+        for section in &chart.sections {
+            let mut section_numbers = Vec::with_capacity(section.lines.len());
+            for line in &section.lines {
+                let mut line_numbers = Vec::with_capacity(line.line.len());
+                for element in &line.line {
+                    match element {
+                        LineElement::Measure { pickup: None, .. } => {
+                            current += 1;
+                            line_numbers.push(Some(current));
+                        }
+                        LineElement::Rehearsal { mark } => {
+                            result.rehearsal_marks.push((mark.clone(), current + 1));
+                            line_numbers.push(None);
+                        }
+                        _ => line_numbers.push(None),
+                    }
+                }
+                section_numbers.push(line_numbers);
+            }
+            result.numbers.push(section_numbers);
+        }
+
+        result.total = current;
+        result
+    }
+
+    /// The number of the measure at `element` in the given section and line.
+    pub fn get(&self, section: usize, line: usize, element: usize) -> Option<u32> {
+        self.numbers
+            .get(section)?
+            .get(line)?
+            .get(element)
+            .copied()?
+    }
+
+    /// Numbers for every element of a line, as used by the renderer.
+    pub fn line(&self, section: usize, line: usize) -> &[Option<u32>] {
+        self.numbers
+            .get(section)
+            .and_then(|s| s.get(line))
+            .map_or(&[], |l| l.as_slice())
+    }
+
+    /// The number of the first measure on a line.
+    pub fn line_start(&self, section: usize, line: usize) -> Option<u32> {
+        self.line(section, line).iter().find_map(|n| *n)
+    }
+
+    /// The bar a rehearsal mark ("A", "B", ...) points at.
+    pub fn rehearsal_mark(&self, mark: &str) -> Option<u32> {
+        self.rehearsal_marks
+            .iter()
+            .find(|(m, _)| m == mark)
+            .map(|(_, number)| *number)
+    }
+
+    pub fn rehearsal_marks(&self) -> &[(String, u32)] {
+        &self.rehearsal_marks
+    }
+
+    /// Total number of measures in the chart.
+    pub fn total(&self) -> u32 {
+        self.total
+    }
+}
@@ -1,15 +1,22 @@
 use anyhow::{Context, Result};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
+use crate::chart::{Chart, Section, TimeSignature};
 use crate::chord::Chord;
-use crate::line::Line;
+use crate::chordpro;
+use crate::line::{Line, LineElement};
 use crate::measure::{Measure, MeasureCollection};
+use crate::shorthand;
+use crate::theory::Mode;
 
 pub struct ChordParser;
 pub struct MeasureParser;
 pub struct MeasureCollectionParser;
 pub struct LineParser;
+pub struct ChartParser;
+pub struct ShorthandParser;
+pub struct ChordProParser;
 
 impl ChordParser {
     pub fn parse_file<P: AsRef<Path>>(path: P) -> Result<Chord> {
@@ -17,13 +24,14 @@ impl ChordParser {
             .with_context(|| format!("No such file or directory: {}", path.as_ref().display()))?;
 
         // First try to parse as YAML
-        let yaml_value: serde_yaml::Value = serde_yaml::from_str(&yaml_content)
+        let mut yaml_value: serde_yaml::Value = serde_yaml::from_str(&yaml_content)
             .with_context(|| "Failed to parse YAML: invalid format")?;
 
         // Check if we have a mapping
         if !yaml_value.is_mapping() {
             anyhow::bail!("Failed to parse YAML: invalid format");
         }
+        infer_qualities(&mut yaml_value);
 
         // Then try to convert to our type
         let result: Result<Chord, serde_yaml::Error> = serde_yaml::from_value(yaml_value);
@@ -55,13 +63,14 @@ impl MeasureParser {
             .with_context(|| format!("No such file or directory: {}", path.as_ref().display()))?;
 
         // First try to parse as YAML
-        let yaml_value: serde_yaml::Value = serde_yaml::from_str(&yaml_content)
+        let mut yaml_value: serde_yaml::Value = serde_yaml::from_str(&yaml_content)
             .with_context(|| "Failed to parse YAML: invalid format")?;
 
         // Check if we have a mapping
         if !yaml_value.is_mapping() {
             anyhow::bail!("Failed to parse YAML: invalid format");
         }
+        infer_qualities(&mut yaml_value);
 
         // Then try to convert to our type
         let result: Result<Measure, serde_yaml::Error> = serde_yaml::from_value(yaml_value);
@@ -96,13 +105,14 @@ impl MeasureCollectionParser {
             .with_context(|| format!("No such file or directory: {}", path.as_ref().display()))?;
 
         // First try to parse as YAML
-        let yaml_value: serde_yaml::Value = serde_yaml::from_str(&yaml_content)
+        let mut yaml_value: serde_yaml::Value = serde_yaml::from_str(&yaml_content)
             .with_context(|| "Failed to parse YAML: invalid format")?;
 
         // Check if we have a mapping
         if !yaml_value.is_mapping() {
             anyhow::bail!("Failed to parse YAML: invalid format");
         }
+        infer_qualities(&mut yaml_value);
 
         // Then try to convert to our type
         let result: Result<MeasureCollection, serde_yaml::Error> =
@@ -142,18 +152,23 @@ impl LineParser {
             .with_context(|| format!("No such file or directory: {}", path.as_ref().display()))?;
 
         // First try to parse as YAML
-        let yaml_value: serde_yaml::Value = serde_yaml::from_str(&yaml_content)
+        let mut yaml_value: serde_yaml::Value = serde_yaml::from_str(&yaml_content)
             .with_context(|| "Failed to parse YAML: invalid format")?;
 
         // Check if we have a mapping
         if !yaml_value.is_mapping() {
             anyhow::bail!("Failed to parse YAML: invalid format");
         }
+        infer_qualities(&mut yaml_value);
 
         // Then try to convert to our type
         let result: Result<Line, serde_yaml::Error> = serde_yaml::from_value(yaml_value);
         match result {
-            Ok(line) => Ok(line),
+            Ok(line) => {
+                validate_line(&line)?;
+                validate_pickups([&line], TimeSignature::default())?;
+                Ok(line)
+            }
             Err(e) => {
                 let msg = e.to_string();
                 if msg.contains("missing field") {
@@ -167,3 +182,353 @@ impl LineParser {
         }
     }
 }
+
+impl ChartParser {
+    /// Parses a sectioned chart. A file containing a single `line` is also
+    /// accepted and treated as a chart with one unnamed section.
+    ///
+    /// `include` entries in `sections` and `lines` are spliced in from other
+    /// files, resolved relative to the including file.
+    pub fn parse_file<P: AsRef<Path>>(path: P) -> Result<Chart> {
+        resolve_chart(Self::read_file(path.as_ref())?)
+    }
+
+    /// Like `parse_file`, but chords written without a quality keep an
+    /// empty one, so that the chart can be written back out as written.
+    pub(crate) fn parse_file_as_written<P: AsRef<Path>>(path: P) -> Result<Chart> {
+        check_chart(Self::read_file(path.as_ref())?)
+    }
+
+    fn read_file(path: &Path) -> Result<Chart> {
+        let yaml_value = load_include(path, &mut Vec::new())?;
+
+        // Then try to convert to our type
+        let result: Result<Chart, serde_yaml::Error> = if yaml_value.get("line").is_some() {
+            serde_yaml::from_value::<Line>(yaml_value).map(Chart::from)
+        } else {
+            serde_yaml::from_value(yaml_value)
+        };
+        match result {
+            Ok(chart) => Ok(chart),
+            Err(e) => {
+                let msg = e.to_string();
+                if msg.contains("missing field") {
+                    anyhow::bail!("Failed to parse YAML: missing required fields");
+                } else if msg.contains("unknown variant") {
+                    anyhow::bail!("Failed to parse YAML: invalid field values");
+                } else {
+                    anyhow::bail!("Failed to parse YAML: {}", e);
+                }
+            }
+        }
+    }
+}
+
+impl ShorthandParser {
+    /// Parses a chart written in the plain-text shorthand described in
+    /// `shorthand`, e.g. `||: 1 4 | 5 1 :||`.
+    pub fn parse_file<P: AsRef<Path>>(path: P) -> Result<Chart> {
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("No such file or directory: {}", path.as_ref().display()))?;
+        Self::parse_str(&text)
+    }
+
+    pub fn parse_str(text: &str) -> Result<Chart> {
+        resolve_chart(shorthand::parse(text)?)
+    }
+}
+
+impl ChordProParser {
+    /// Imports a ChordPro song, numbering its letter chords in the song's
+    /// `{key}`.
+    pub fn parse_file<P: AsRef<Path>>(path: P) -> Result<Chart> {
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("No such file or directory: {}", path.as_ref().display()))?;
+        Self::parse_str(&text)
+    }
+
+    pub fn parse_str(text: &str) -> Result<Chart> {
+        resolve_chart(chordpro::import(text)?)
+    }
+}
+
+/// Checks a freshly read chart and resolves its references and implied
+/// chord qualities.
+fn resolve_chart(chart: Chart) -> Result<Chart> {
+    let mut chart = check_chart(chart)?;
+    chart.infer_qualities();
+    Ok(chart)
+}
+
+/// Checks a freshly read chart and resolves its references, leaving chord
+/// qualities as written.
+fn check_chart(mut chart: Chart) -> Result<Chart> {
+    if let Some(capo) = chart.capo.filter(|capo| !(1..=11).contains(capo)) {
+        anyhow::bail!("Capo must be between 1 and 11, got: {}", capo);
+    }
+    if chart.tempo == Some(0) {
+        anyhow::bail!("Tempo must be greater than 0");
+    }
+    resolve_references(&mut chart)?;
+    for section in &chart.sections {
+        for line in &section.lines {
+            validate_line(line)?;
+        }
+        validate_pickups(&section.lines, chart.time)?;
+    }
+    Ok(chart)
+}
+
+/// Fills in the quality of chords written without one. Chord, measure and
+/// line files carry no key, so their chords take the major-key diatonic
+/// quality; charts resolve theirs from the chart's mode instead.
+fn infer_qualities(value: &mut serde_yaml::Value) {
+    match value {
+        serde_yaml::Value::Mapping(map) => {
+            if let Some(degree) = map.get("degree").and_then(serde_yaml::Value::as_i64) {
+                if !map.contains_key("quality") {
+                    let accidental = map
+                        .get("accidental")
+                        .and_then(|a| serde_yaml::from_value(a.clone()).ok());
+                    let quality = Mode::Major.diatonic_quality(degree as i32, accidental);
+                    map.insert("quality".into(), quality.name().into());
+                }
+            }
+            map.values_mut().for_each(infer_qualities);
+        }
+        serde_yaml::Value::Sequence(items) => items.iter_mut().for_each(infer_qualities),
+        _ => {}
+    }
+}
+
+/// Reads a chart, section or line file and splices its includes. `chain`
+/// holds the files currently being included, outermost first, so that a
+/// cycle can be reported with the full include chain.
+fn load_include(path: &Path, chain: &mut Vec<(PathBuf, PathBuf)>) -> Result<serde_yaml::Value> {
+    let canonical = fs::canonicalize(path)
+        .with_context(|| format!("No such file or directory: {}", path.display()))?;
+    if chain.iter().any(|(seen, _)| *seen == canonical) {
+        let cycle: Vec<String> = chain
+            .iter()
+            .map(|(_, shown)| shown.display().to_string())
+            .chain(std::iter::once(path.display().to_string()))
+            .collect();
+        anyhow::bail!("Include cycle detected: {}", cycle.join(" -> "));
+    }
+
+    let yaml_content = fs::read_to_string(path)
+        .with_context(|| format!("No such file or directory: {}", path.display()))?;
+
+    // First try to parse as YAML
+    let mut yaml_value: serde_yaml::Value = serde_yaml::from_str(&yaml_content)
+        .with_context(|| "Failed to parse YAML: invalid format")?;
+
+    // Check if we have a mapping
+    if !yaml_value.is_mapping() {
+        anyhow::bail!("Failed to parse YAML: invalid format");
+    }
+
+    chain.push((canonical, path.to_path_buf()));
+    let base = path.parent().unwrap_or(Path::new(""));
+    splice_includes(&mut yaml_value, "sections", base, chain)?;
+    splice_includes(&mut yaml_value, "lines", base, chain)?;
+    if let Some(sections) = yaml_value
+        .get_mut("sections")
+        .and_then(|v| v.as_sequence_mut())
+    {
+        for section in sections {
+            splice_includes(section, "lines", base, chain)?;
+        }
+    }
+    chain.pop();
+
+    Ok(yaml_value)
+}
+
+/// Replaces each `include: <path>` entry of the `key` sequence with the
+/// included file's contents. A chart file contributes all of its sections,
+/// a section or line file contributes itself.
+fn splice_includes(
+    value: &mut serde_yaml::Value,
+    key: &str,
+    base: &Path,
+    chain: &mut Vec<(PathBuf, PathBuf)>,
+) -> Result<()> {
+    let Some(entries) = value.get_mut(key).and_then(|v| v.as_sequence_mut()) else {
+        return Ok(());
+    };
+
+    let mut spliced = Vec::with_capacity(entries.len());
+    for entry in entries.drain(..) {
+        let Some(include) = entry.get("include") else {
+            spliced.push(entry);
+            continue;
+        };
+        let Some(include) = include.as_str() else {
+            anyhow::bail!("Failed to parse YAML: include must be a file path");
+        };
+
+        let included_path = base.join(include);
+        let included = load_include(&included_path, chain)?;
+        let nested_sections = included.get("sections").and_then(|v| v.as_sequence());
+        match (key, nested_sections) {
+            ("sections", Some(sections)) => spliced.extend(sections.iter().cloned()),
+            ("sections", None) if included.get("lines").is_some() => spliced.push(included),
+            ("lines", None) if included.get("line").is_some() => spliced.push(included),
+            _ => anyhow::bail!(
+                "Included file {} does not contain a {}",
+                included_path.display(),
+                if key == "sections" {
+                    "chart or section"
+                } else {
+                    "line"
+                }
+            ),
+        }
+    }
+    *entries = spliced;
+    Ok(())
+}
+
+fn validate_line(line: &Line) -> Result<()> {
+    let mut previous_chords: Option<usize> = None;
+
+    for element in &line.line {
+        match element {
+            LineElement::Measure { measure, .. } => previous_chords = Some(measure.len()),
+            LineElement::Annotation { annotation } => {
+                let Some(chord_count) = previous_chords else {
+                    anyhow::bail!("Annotation \"{}\" must follow a measure", annotation.text);
+                };
+                if let Some(chord) = annotation.chord {
+                    if chord == 0 || chord > chord_count {
+                        anyhow::bail!(
+                            "Annotation \"{}\" refers to chord {}, but the measure has {} chord(s)",
+                            annotation.text,
+                            chord,
+                            chord_count
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Fills in the lines of every `ref` section from the first earlier section
+/// defined under that name, then applies its per-instance overrides.
+fn resolve_references(chart: &mut Chart) -> Result<()> {
+    for index in 0..chart.sections.len() {
+        let section = &chart.sections[index];
+        let Some(reference) = section.reference.clone() else {
+            if section.lines.is_empty() {
+                anyhow::bail!("{} has neither lines nor a ref", describe(section, index));
+            }
+            continue;
+        };
+        if !section.lines.is_empty() {
+            anyhow::bail!(
+                "{} cannot have both lines and a ref",
+                describe(section, index)
+            );
+        }
+
+        let definition = chart.sections[..index]
+            .iter()
+            .find(|s| s.reference.is_none() && s.name == reference);
+        let Some(definition) = definition else {
+            if chart.sections[index].name == reference {
+                anyhow::bail!("Section \"{}\" cannot reference itself", reference);
+            }
+            if chart.sections[index + 1..]
+                .iter()
+                .any(|s| s.reference.is_none() && s.name == reference)
+            {
+                anyhow::bail!(
+                    "Section \"{}\" is referenced before it is defined",
+                    reference
+                );
+            }
+            anyhow::bail!("Unknown section reference: {}", reference);
+        };
+        let mut lines = definition.lines.clone();
+
+        let section = &mut chart.sections[index];
+        if let Some(last_measure) = &section.last_measure {
+            let measure = lines
+                .iter_mut()
+                .rev()
+                .flat_map(|line| line.line.iter_mut().rev())
+                .find_map(|element| match element {
+                    LineElement::Measure { measure, .. } => Some(measure),
+                    _ => None,
+                });
+            match measure {
+                Some(measure) => *measure = last_measure.clone(),
+                None => anyhow::bail!(
+                    "Cannot override the last measure of \"{}\": it has no measures",
+                    reference
+                ),
+            }
+        }
+        if section.name.is_empty() {
+            section.name = reference;
+        }
+        section.lines = lines;
+    }
+    Ok(())
+}
+
+/// Names a section in errors by its name, or by its position if it has none.
+fn describe(section: &Section, index: usize) -> String {
+    if section.name.is_empty() {
+        format!("Section {}", index + 1)
+    } else {
+        format!("Section \"{}\"", section.name)
+    }
+}
+
+/// A pickup is only allowed as the first measure of a section (or of a
+/// bare line file) and must be shorter than a full bar.
+fn validate_pickups<'a>(
+    lines: impl IntoIterator<Item = &'a Line>,
+    time: TimeSignature,
+) -> Result<()> {
+    let measures =
+        lines
+            .into_iter()
+            .flat_map(|line| &line.line)
+            .filter_map(|element| match element {
+                LineElement::Measure { pickup, .. } => Some(*pickup),
+                _ => None,
+            });
+
+    for (index, pickup) in measures.enumerate() {
+        let Some(beats) = pickup else {
+            continue;
+        };
+        if index > 0 {
+            anyhow::bail!(
+                "Pickup must be the first measure of a section, found one at measure {}",
+                index + 1
+            );
+        }
+        if time.beats == 1 {
+            anyhow::bail!(
+                "Pickups are not allowed in {}: a bar is a single beat",
+                time
+            );
+        }
+        if beats == 0 || beats >= time.beats {
+            anyhow::bail!(
+                "Pickup must be between 1 and {} beats in {}, got: {}",
+                time.beats - 1,
+                time,
+                beats
+            );
+        }
+    }
+    Ok(())
+}
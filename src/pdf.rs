@@ -0,0 +1,439 @@
+//! Printable PDF output: a chart laid out over as many Letter or A4 pages
+//! as it needs, with the fonts embedded.
+
+use crate::chart::{Chart, Section};
+use crate::layout::{Glyph, GlyphKind, Layout, RenderBackend};
+use crate::line::{AnnotationKind, RepeatSign};
+use crate::renderer::{system_fonts, truncate_text, CHORD_SPACING};
+use anyhow::Result;
+use printpdf::path::PaintMode;
+use printpdf::{
+    calculate_points_for_circle, Color, Greyscale, IndirectFontRef, Mm, PdfDocument,
+    PdfDocumentReference, PdfLayerReference, Point, Polygon, Pt, Rect,
+};
+use resvg::usvg::fontdb::{Family, Query, Style, Weight};
+use std::ops::Range;
+
+/// Page margin on every side, in points.
+pub const MARGIN: f32 = 54.0;
+/// Room for the title, key and page number at the top of each page.
+pub const HEADER_HEIGHT: f32 = 36.0;
+pub const SECTION_LABEL_HEIGHT: f32 = 22.0;
+pub const ROW_HEIGHT: f32 = 48.0;
+pub const SECTION_GAP: f32 = 12.0;
+/// Points per SVG unit: a bar of one chord is 50pt wide unless its line
+/// has to shrink to fit the page, when its text shrinks with it.
+const SCALE: f32 = 0.5;
+const CHORD_FONT_SIZE: f32 = 14.0;
+const SMALL_FONT_SIZE: f32 = 8.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum PaperSize {
+    #[default]
+    Letter,
+    A4,
+}
+
+impl PaperSize {
+    /// Width and height in points.
+    pub fn size(&self) -> (f32, f32) {
+        match self {
+            PaperSize::Letter => (612.0, 792.0),
+            PaperSize::A4 => (595.28, 841.89),
+        }
+    }
+}
+
+/// The lines of one section that fall on a page.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PageRun {
+    pub section: usize,
+    pub lines: Range<usize>,
+}
+
+/// Splits a chart into pages of runs of section lines. Sections move whole
+/// to a new page when they do not fit on the current one; only a section
+/// taller than a page breaks, and then only between lines, keeping its
+/// label with its first line.
+pub fn paginate(chart: &Chart, layout: &Layout, paper: PaperSize) -> Vec<Vec<PageRun>> {
+    let capacity = paper.size().1 - 2.0 * MARGIN - HEADER_HEIGHT;
+    let mut pages: Vec<Vec<PageRun>> = vec![Vec::new()];
+    let mut used = 0.0;
+
+    for (index, section) in chart.sections.iter().enumerate() {
+        let label = if section.name.is_empty() {
+            0.0
+        } else {
+            SECTION_LABEL_HEIGHT
+        };
+        let rows = row_count(section, layout);
+        let height = label + rows as f32 * ROW_HEIGHT;
+        let gap = if used > 0.0 { SECTION_GAP } else { 0.0 };
+
+        if used > 0.0 && used + gap + height > capacity {
+            let fits_a_page = height <= capacity;
+            let first_row_fits = used + gap + label + ROW_HEIGHT <= capacity;
+            if fits_a_page || !first_row_fits {
+                pages.push(Vec::new());
+                used = 0.0;
+            }
+        }
+        used += if used > 0.0 { SECTION_GAP } else { 0.0 } + label;
+
+        let mut start = 0;
+        for row in 0..rows {
+            if used + ROW_HEIGHT > capacity && row > start {
+                pages.last_mut().unwrap().push(PageRun {
+                    section: index,
+                    lines: start..row,
+                });
+                pages.push(Vec::new());
+                used = 0.0;
+                start = row;
+            }
+            used += ROW_HEIGHT;
+        }
+        pages.last_mut().unwrap().push(PageRun {
+            section: index,
+            lines: start..rows,
+        });
+    }
+    pages
+}
+
+/// Rows a section takes: its lines, or one for a `(Chorus)` placeholder.
+fn row_count(section: &Section, layout: &Layout) -> usize {
+    if section.is_reference() && !layout.expands_references() {
+        1
+    } else {
+        section.lines.len()
+    }
+}
+
+/// Writes `chart` as a PDF laid out by `layout`: the same notation, key,
+/// capo shapes, references and measure numbers as the SVG. Each page has a
+/// header with the title, key and page number.
+pub fn write(chart: &Chart, layout: &Layout, paper: PaperSize) -> Result<Vec<u8>> {
+    let (width, height) = paper.size();
+    let title = chart.title.as_deref().unwrap_or("");
+    let (document, page, layer) =
+        PdfDocument::new(title, Mm::from(Pt(width)), Mm::from(Pt(height)), "Chart");
+    let fonts = Fonts::load(&document)?;
+
+    let numbers = layout.measure_numbers(chart);
+    let pages = paginate(chart, layout, paper);
+    let key = layout.key().copied().or(chart.key);
+
+    for (page_index, runs) in pages.iter().enumerate() {
+        let layer = if page_index == 0 {
+            document.get_page(page).get_layer(layer)
+        } else {
+            let (page, layer) =
+                document.add_page(Mm::from(Pt(width)), Mm::from(Pt(height)), "Chart");
+            document.get_page(page).get_layer(layer)
+        };
+        let mut canvas = Canvas {
+            layer,
+            fonts: &fonts,
+            height,
+            top: MARGIN + HEADER_HEIGHT,
+            scale: SCALE,
+        };
+
+        // Header: title on the left, key in the middle, page on the right
+        let baseline = MARGIN + 14.0;
+        canvas.text(title, MARGIN, baseline, 14.0, &fonts.bold, Align::Left);
+        if let Some(key) = key {
+            let label = format!("Key: {}", key);
+            let center = width / 2.0;
+            canvas.text(
+                &label,
+                center,
+                baseline,
+                10.0,
+                &fonts.regular,
+                Align::Center,
+            );
+        }
+        let page_label = format!("Page {}/{}", page_index + 1, pages.len());
+        let right = width - MARGIN;
+        canvas.text(
+            &page_label,
+            right,
+            baseline,
+            10.0,
+            &fonts.regular,
+            Align::Right,
+        );
+        canvas.line(MARGIN, MARGIN + 22.0, right, MARGIN + 22.0, 0.75);
+
+        for (run_index, run) in runs.iter().enumerate() {
+            let section = &chart.sections[run.section];
+            if run_index > 0 && run.lines.start == 0 {
+                canvas.top += SECTION_GAP;
+            }
+            canvas.scale = SCALE;
+            if run.lines.start == 0 && !section.name.is_empty() {
+                canvas.draw(&layout.section_label(&section.name, 0, 0));
+                canvas.top += SECTION_LABEL_HEIGHT;
+            }
+            if let (Some(reference), false) = (&section.reference, layout.expands_references()) {
+                for glyph in layout.placeholder(reference, section, 0, 0) {
+                    canvas.draw(&glyph);
+                }
+                canvas.top += ROW_HEIGHT;
+                continue;
+            }
+            for line_index in run.lines.clone() {
+                let mut glyphs = Vec::new();
+                let numbers = numbers.line(run.section, line_index);
+                let line = &section.lines[line_index];
+                let end = layout.numbered_line(line, numbers, chart.time, 0, 0, &mut glyphs);
+
+                // Lines too long for the page are shrunk to fit
+                let available = width - 2.0 * MARGIN;
+                canvas.scale = SCALE.min(available / end.max(1) as f32);
+                for glyph in &glyphs {
+                    canvas.draw(glyph);
+                }
+                canvas.top += ROW_HEIGHT;
+            }
+        }
+    }
+
+    document
+        .save_to_bytes()
+        .map_err(|e| anyhow::anyhow!("Failed to write PDF: {}", e))
+}
+
+fn grey(level: f32) -> Color {
+    Color::Greyscale(Greyscale::new(level, None))
+}
+
+#[derive(Clone, Copy)]
+enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+/// An embedded font, kept alongside its data to measure text with.
+struct Font {
+    data: Vec<u8>,
+    index: u32,
+    reference: IndirectFontRef,
+}
+
+impl Font {
+    /// Width of `text` in points at `size`.
+    fn width(&self, text: &str, size: f32) -> f32 {
+        let Ok(face) = ttf_parser::Face::parse(&self.data, self.index) else {
+            return 0.0;
+        };
+        let units: u32 = text
+            .chars()
+            .filter_map(|c| face.glyph_index(c))
+            .filter_map(|glyph| face.glyph_hor_advance(glyph))
+            .map(u32::from)
+            .sum();
+        units as f32 * size / face.units_per_em() as f32
+    }
+}
+
+struct Fonts {
+    regular: Font,
+    bold: Font,
+    italic: Font,
+}
+
+impl Fonts {
+    /// Embeds Arial, or the system's sans-serif face, in regular, bold and
+    /// italic; styles that are not installed fall back to regular.
+    fn load(document: &PdfDocumentReference) -> Result<Self> {
+        let database = system_fonts();
+        let families = [Family::Name("Arial"), Family::SansSerif];
+        let load = |weight: Weight, style: Style| -> Option<Font> {
+            let id = database.query(&Query {
+                families: &families,
+                weight,
+                style,
+                ..Query::default()
+            })?;
+            let (data, index) =
+                database.with_face_data(id, |data, index| (data.to_vec(), index))?;
+            let reference = document.add_external_font(data.as_slice()).ok()?;
+            Some(Font {
+                data,
+                index,
+                reference,
+            })
+        };
+
+        let regular = load(Weight::NORMAL, Style::Normal)
+            .ok_or_else(|| anyhow::anyhow!("Failed to write PDF: no sans-serif font to embed"))?;
+        let bold = load(Weight::BOLD, Style::Normal);
+        let italic = load(Weight::NORMAL, Style::Italic);
+        let copy = |font: &Font| Font {
+            data: font.data.clone(),
+            index: font.index,
+            reference: font.reference.clone(),
+        };
+        Ok(Self {
+            bold: bold.unwrap_or_else(|| copy(&regular)),
+            italic: italic.unwrap_or_else(|| copy(&regular)),
+            regular,
+        })
+    }
+}
+
+/// A page's layer, drawn on in points from the top-left corner. Glyphs are
+/// drawn into the row `top` points down the page, with the first chord of a
+/// line laid out at x = 0 half a bar in from the left margin and SVG units
+/// scaled by `scale`.
+struct Canvas<'a> {
+    layer: PdfLayerReference,
+    fonts: &'a Fonts,
+    height: f32,
+    top: f32,
+    scale: f32,
+}
+
+impl Canvas<'_> {
+    /// How much the current line is shrunk, applied to its text so that
+    /// labels keep clear of each other.
+    fn zoom(&self) -> f32 {
+        self.scale / SCALE
+    }
+
+    fn point(&self, x: f32, y: f32) -> Point {
+        Point::new(Mm::from(Pt(x)), Mm::from(Pt(self.height - y)))
+    }
+
+    /// Writes `text` with its baseline at `y`.
+    fn text(&self, text: &str, x: f32, y: f32, size: f32, font: &Font, align: Align) {
+        let x = match align {
+            Align::Left => x,
+            Align::Center => x - font.width(text, size) / 2.0,
+            Align::Right => x - font.width(text, size),
+        };
+        let point = self.point(x, y);
+        self.layer
+            .use_text(text, size, point.x.into(), point.y.into(), &font.reference);
+    }
+
+    fn line(&self, x1: f32, y1: f32, x2: f32, y2: f32, thickness: f32) {
+        self.layer.set_outline_thickness(thickness);
+        self.layer.add_line(printpdf::Line {
+            points: vec![(self.point(x1, y1), false), (self.point(x2, y2), false)],
+            is_closed: false,
+        });
+    }
+
+    fn dot(&self, x: f32, y: f32, radius: f32) {
+        let center = self.point(x, y);
+        self.layer.add_polygon(Polygon {
+            rings: vec![calculate_points_for_circle(Pt(radius), center.x, center.y)],
+            mode: PaintMode::Fill,
+            ..Polygon::default()
+        });
+    }
+
+    fn rect(&self, left: f32, top: f32, right: f32, bottom: f32) {
+        self.layer.set_outline_thickness(0.75);
+        let (lower_left, upper_right) = (self.point(left, bottom), self.point(right, top));
+        self.layer.add_rect(
+            Rect::new(
+                lower_left.x.into(),
+                lower_left.y.into(),
+                upper_right.x.into(),
+                upper_right.y.into(),
+            )
+            .with_mode(PaintMode::Stroke),
+        );
+    }
+}
+
+impl RenderBackend for Canvas<'_> {
+    fn draw(&mut self, glyph: &Glyph) {
+        let half = CHORD_SPACING as f32 * self.scale / 2.0;
+        let x = MARGIN + half + glyph.x as f32 * self.scale;
+        let y = self.top + ROW_HEIGHT / 2.0;
+        let fonts = self.fonts;
+        let zoom = self.zoom();
+
+        match &glyph.kind {
+            // The page is white and the header names the chart
+            GlyphKind::Background
+            | GlyphKind::Measure { .. }
+            | GlyphKind::RoadMap(_)
+            | GlyphKind::CapoMarker(_) => {}
+            GlyphKind::Chord { label, shape } => {
+                let font = &fonts.regular;
+                let size = CHORD_FONT_SIZE * zoom;
+                self.text(label, x, y + 5.0, size, font, Align::Center);
+                if let Some(shape) = shape {
+                    self.layer.set_fill_color(grey(0.5));
+                    self.text(shape, x, y + 16.0, 9.0 * zoom, font, Align::Center);
+                    self.layer.set_fill_color(grey(0.0));
+                }
+            }
+            GlyphKind::MeasureNumber(number) => {
+                let (left, top) = (x - half + 2.0, self.top + 9.0);
+                self.text(
+                    &number.to_string(),
+                    left,
+                    top,
+                    7.0 * zoom,
+                    &fonts.regular,
+                    Align::Left,
+                );
+            }
+            GlyphKind::RehearsalMark(mark) => {
+                let size = 10.0 * zoom;
+                let width = fonts.bold.width(mark, size) + 6.0;
+                // Kept inside the margin when the mark starts the line
+                let center = (x - half).max(MARGIN + width / 2.0);
+                self.rect(center - width / 2.0, y - 7.0, center + width / 2.0, y + 7.0);
+                self.text(mark, center, y + 3.5, size, &fonts.bold, Align::Center);
+            }
+            GlyphKind::Repeat(repeat) => {
+                let (top, bottom) = (y - ROW_HEIGHT / 3.0, y + ROW_HEIGHT / 3.0);
+                self.line(x, top, x, bottom, 1.5);
+                self.line(x + 2.5, top, x + 2.5, bottom, 1.5);
+                let dot_x = match repeat {
+                    RepeatSign::Begin => x + 6.0,
+                    RepeatSign::End => x - 3.5,
+                };
+                self.dot(dot_x, y - 3.5, 1.2);
+                self.dot(dot_x, y + 3.5, 1.2);
+            }
+            GlyphKind::Spacer => self.dot(x, y, 2.0),
+            GlyphKind::Annotation { text, kind, width } => {
+                let (row_y, font) = match kind {
+                    AnnotationKind::Note => (y - 12.0, &fonts.regular),
+                    AnnotationKind::Lyric => (y + 20.0, &fonts.italic),
+                };
+                // Shorten the text until it fits under its chords
+                let width = (*width).max(CHORD_SPACING) as f32 * self.scale;
+                let size = SMALL_FONT_SIZE * zoom;
+                let mut chars = text.chars().count();
+                let mut shown = text.clone();
+                while chars > 1 && font.width(&shown, size) > width {
+                    chars -= 1;
+                    shown = truncate_text(text, chars);
+                }
+                let left = x - half * 2.0 / 3.0;
+                self.text(&shown, left, row_y, size, font, Align::Left);
+            }
+            GlyphKind::SectionLabel(name) => {
+                let top = self.top + 14.0;
+                self.text(name, x - half, top, 12.0, &fonts.bold, Align::Left);
+            }
+            GlyphKind::Placeholder(reference) => {
+                let text = format!("({})", reference);
+                let left = x - half * 2.0 / 3.0;
+                self.text(&text, left, y + 4.0, 12.0, &fonts.italic, Align::Left);
+            }
+        }
+    }
+}
@@ -1,192 +1,758 @@
-use crate::chord::{Chord, ChordQuality};
-use crate::line::{Line, LineElement, RepeatSign};
+use crate::chart::{Chart, Section};
+use crate::chord::Chord;
+use crate::form::Form;
+use crate::layout::{Glyph, GlyphKind, Layout, RenderBackend};
+use crate::line::{Annotation, AnnotationKind, Line, RepeatSign};
 use crate::measure::Measure;
+use crate::theory::Key;
 use anyhow::Result;
+use resvg::{tiny_skia, usvg};
+use std::sync::{Arc, OnceLock};
 use svg::node::element::{Circle, Group, Line as SvgLine, Rectangle, Text};
-use svg::node::Text as TextNode;
+use svg::node::{Node, Text as TextNode};
 use svg::Document;
 
-pub const SVG_WIDTH: i32 = 800;
-pub const SVG_HEIGHT: i32 = 400;
 pub const CHORD_SPACING: i32 = 100;
 pub const LINE_HEIGHT: i32 = 100;
 pub const REPEAT_DOT_SPACING: i32 = 6;
 pub const REPEAT_LINE_SPACING: i32 = 3;
 pub const SPACER_DOT_RADIUS: i32 = 3;
+pub const SECTION_LABEL_HEIGHT: i32 = 30;
+pub const ANNOTATION_FONT_SIZE: i32 = 12;
+pub const ANNOTATION_OFFSET: i32 = 28;
+pub const MEASURE_NUMBER_FONT_SIZE: i32 = 10;
+pub const REHEARSAL_MARK_SIZE: i32 = 20;
+pub const CAPO_SHAPE_FONT_SIZE: i32 = 14;
+pub const CAPO_SHAPE_OFFSET: i32 = 18;
+/// Arial, or the system's sans-serif face where it is not installed.
+pub const FONT_FAMILY: &str = "Arial, sans-serif";
+/// The resolution at which one SVG unit is one pixel.
+pub const DEFAULT_DPI: f32 = 96.0;
+/// Space left around the drawn content on every side of the canvas.
+pub const DEFAULT_MARGIN: i32 = 20;
+
+/// A rectangle in SVG units, from its top-left corner.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bounds {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Bounds {
+    pub fn new(x: i32, y: i32, width: i32, height: i32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn right(&self) -> i32 {
+        self.x + self.width
+    }
+
+    pub fn bottom(&self) -> i32 {
+        self.y + self.height
+    }
+
+    /// The smallest rectangle holding both.
+    pub fn union(&self, other: &Bounds) -> Bounds {
+        let (x, y) = (self.x.min(other.x), self.y.min(other.y));
+        let right = self.right().max(other.right());
+        let bottom = self.bottom().max(other.bottom());
+        Bounds::new(x, y, right - x, bottom - y)
+    }
+
+    /// Grown by `margin` on every side.
+    pub fn expand(&self, margin: i32) -> Bounds {
+        Bounds::new(
+            self.x - margin,
+            self.y - margin,
+            self.width + 2 * margin,
+            self.height + 2 * margin,
+        )
+    }
+}
+
+/// Raster formats that `ChordRenderer::save_image` can write.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    WebP,
+}
+
+impl ImageFormat {
+    /// The format a file name's extension calls for, if it is a raster one.
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = path.rsplit_once('.')?.1.to_ascii_lowercase();
+        match extension.as_str() {
+            "png" => Some(Self::Png),
+            "jpg" | "jpeg" => Some(Self::Jpeg),
+            "webp" => Some(Self::WebP),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Clone, Copy)]
 pub enum NotationType {
     Regular,
     Compact,
+    /// Roman numerals, upper case for major and lower case for minor
+    /// ("I vi IV V"), with ° and + for diminished and augmented.
+    Roman,
+    /// Movable-do solfège ("do la fa sol"), with chromatic syllables such as
+    /// "te" for b7.
+    Solfege,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MeasureNumbering {
+    Off,
+    LineStarts,
+    EveryBar,
+}
+
+/// Which letter names of a capo chart are drawn large, with the other set
+/// beneath each chord in smaller type.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CapoDisplay {
+    SoundingAbove,
+    ShapesAbove,
 }
 
+/// Lays charts out and draws them as SVG, which can then be saved or
+/// rasterized.
 pub struct ChordRenderer {
-    document: Document,
-    notation_type: NotationType,
+    layout: Layout,
+    backend: SvgBackend,
+}
+
+impl Default for ChordRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ChordRenderer {
     pub fn new() -> Self {
-        let document = Document::new()
-            .set("width", SVG_WIDTH)
-            .set("height", SVG_HEIGHT)
-            .set("viewBox", (0, 0, SVG_WIDTH, SVG_HEIGHT));
+        Self::with_notation(NotationType::Regular)
+    }
 
+    pub fn with_notation(notation_type: NotationType) -> Self {
         Self {
-            document,
-            notation_type: NotationType::Regular,
+            layout: Layout::with_notation(notation_type),
+            backend: SvgBackend::new(),
         }
     }
 
-    pub fn with_notation(notation_type: NotationType) -> Self {
-        let document = Document::new()
-            .set("width", SVG_WIDTH)
-            .set("height", SVG_HEIGHT)
-            .set("viewBox", (0, 0, SVG_WIDTH, SVG_HEIGHT));
+    /// The layout settings, shared with the other backends.
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
 
-        Self {
-            document,
-            notation_type,
-        }
+    /// Whether `ref` sections are drawn in full (the default) or as a
+    /// one-line placeholder naming the section they repeat.
+    pub fn set_expand_references(&mut self, expand: bool) -> &mut Self {
+        self.layout.set_expand_references(expand);
+        self
     }
 
-    pub fn render_chord(&mut self, chord: &Chord, x: i32, y: i32) -> &mut Self {
-        let chord_group = self.create_chord_group(chord, x, y);
-        self.document = self.document.clone().add(chord_group);
+    /// Bar numbers are drawn by `render_chart`, which numbers the whole chart.
+    pub fn set_measure_numbering(&mut self, numbering: MeasureNumbering) -> &mut Self {
+        self.layout.set_measure_numbering(numbering);
         self
     }
 
-    pub fn render_measure(&mut self, measure: &Measure, x: i32, y: i32) -> &mut Self {
-        let mut current_x = x;
-        for chord in measure.get_chords() {
-            self.render_chord(chord, current_x, y);
-            current_x += CHORD_SPACING;
-        }
+    /// With a key set, chords are rendered as letter names in that key
+    /// ("Am", "Bb/D") instead of numbers.
+    pub fn set_key(&mut self, key: Option<Key>) -> &mut Self {
+        self.layout.set_key(key);
         self
     }
 
-    pub fn render_line(&mut self, line: &Line, x: i32, y: i32) -> &mut Self {
-        let mut current_x = x;
-
-        for element in &line.line {
-            match element {
-                LineElement::Measure { measure } => {
-                    let measure = Measure::from(measure.clone());
-                    self.render_measure(&measure, current_x, y);
-                    current_x += CHORD_SPACING * measure.get_chords().len() as i32;
-                }
-                LineElement::Repeat { repeat } => {
-                    self.render_repeat(repeat, current_x, y);
-                    current_x += CHORD_SPACING / 2; // Half spacing for repeats
-                }
-                LineElement::Spacer => {
-                    self.render_spacer(current_x, y);
-                    current_x += CHORD_SPACING / 2; // Half spacing for spacers
-                }
-            }
-        }
+    /// With both a key and a capo shapes key set, each chord is drawn in
+    /// both keys, e.g. "A" sounding over a "G" shape with a capo on 2.
+    pub fn set_capo_shapes(&mut self, shapes_key: Option<Key>) -> &mut Self {
+        self.layout.set_capo_shapes(shapes_key);
         self
     }
 
-    pub fn render_repeat(&mut self, repeat_type: &RepeatSign, x: i32, y: i32) -> &mut Self {
-        let mut group = Group::new();
-        let center_y = y;
-
-        // Add two vertical lines
-        let line1 = SvgLine::new()
-            .set("x1", x)
-            .set("y1", center_y - LINE_HEIGHT / 3)
-            .set("x2", x)
-            .set("y2", center_y + LINE_HEIGHT / 3)
-            .set("stroke", "black")
-            .set("stroke-width", 2);
-
-        let line2 = SvgLine::new()
-            .set("x1", x + REPEAT_LINE_SPACING)
-            .set("y1", center_y - LINE_HEIGHT / 3)
-            .set("x2", x + REPEAT_LINE_SPACING)
-            .set("y2", center_y + LINE_HEIGHT / 3)
-            .set("stroke", "black")
-            .set("stroke-width", 2);
-
-        group = group.add(line1).add(line2);
-
-        // Add dots based on repeat type
-        let dot_x = match repeat_type {
-            RepeatSign::Begin => x + REPEAT_LINE_SPACING + REPEAT_DOT_SPACING,
-            RepeatSign::End => x - REPEAT_DOT_SPACING,
-        };
+    pub fn set_capo_display(&mut self, display: CapoDisplay) -> &mut Self {
+        self.layout.set_capo_display(display);
+        self
+    }
+
+    /// Space left around the content; the canvas grows to fit whatever is
+    /// drawn.
+    pub fn set_margin(&mut self, margin: i32) -> &mut Self {
+        self.backend.set_margin(margin);
+        self
+    }
 
-        let dot1 = Circle::new()
-            .set("cx", dot_x)
-            .set("cy", center_y - REPEAT_DOT_SPACING)
-            .set("r", 2)
-            .set("fill", "black");
+    /// The area drawn on so far, without margins, or `None` before anything
+    /// but the background is drawn.
+    pub fn bounds(&self) -> Option<Bounds> {
+        self.backend.bounds()
+    }
 
-        let dot2 = Circle::new()
-            .set("cx", dot_x)
-            .set("cy", center_y + REPEAT_DOT_SPACING)
-            .set("r", 2)
-            .set("fill", "black");
+    /// The canvas the chart is saved on: the content bounds plus margins.
+    pub fn view_box(&self) -> Bounds {
+        self.backend.view_box()
+    }
 
-        group = group.add(dot1).add(dot2);
-        self.document = self.document.clone().add(group);
+    fn draw(&mut self, glyphs: &[Glyph]) -> &mut Self {
+        for glyph in glyphs {
+            self.backend.draw(glyph);
+        }
         self
     }
 
+    pub fn render_chord(&mut self, chord: &Chord, x: i32, y: i32) -> &mut Self {
+        let glyph = self.layout.chord(chord, x, y);
+        self.draw(&[glyph])
+    }
+
+    pub fn render_measure(&mut self, measure: &Measure, x: i32, y: i32) -> &mut Self {
+        let glyphs = self.layout.measure(measure, x, y);
+        self.draw(&glyphs)
+    }
+
+    pub fn render_line(&mut self, line: &Line, x: i32, y: i32) -> &mut Self {
+        let glyphs = self.layout.line(line, x, y);
+        self.draw(&glyphs)
+    }
+
+    /// Renders a bar number above the top-left corner of the measure whose
+    /// first chord is centered at `x`.
+    pub fn render_measure_number(&mut self, number: u32, x: i32, y: i32) -> &mut Self {
+        let kind = GlyphKind::MeasureNumber(number);
+        self.draw(&[Glyph { x, y, kind }])
+    }
+
+    /// Renders a boxed rehearsal letter in the gap before the measure whose
+    /// first chord is centered at `x`.
+    pub fn render_rehearsal_mark(&mut self, mark: &str, x: i32, y: i32) -> &mut Self {
+        let kind = GlyphKind::RehearsalMark(mark.to_string());
+        self.draw(&[Glyph { x, y, kind }])
+    }
+
+    /// Renders an annotation in the text row above (notes) or below (lyrics)
+    /// the chord centered at `x`. Text that does not fit in `width` is
+    /// truncated with an ellipsis.
+    pub fn render_annotation(
+        &mut self,
+        annotation: &Annotation,
+        x: i32,
+        y: i32,
+        width: i32,
+    ) -> &mut Self {
+        let glyph = self.layout.annotation(annotation, x, y, width);
+        self.draw(&[glyph])
+    }
+
+    pub fn render_chart(&mut self, chart: &Chart, x: i32, y: i32) -> &mut Self {
+        let glyphs = self.layout.chart(chart, x, y);
+        self.draw(&glyphs)
+    }
+
+    pub fn render_section_label(&mut self, name: &str, x: i32, y: i32) -> &mut Self {
+        let glyph = self.layout.section_label(name, x, y);
+        self.draw(&[glyph])
+    }
+
+    /// Renders "(Chorus)" in place of a referenced section's lines, followed
+    /// by its replacement last measure if it overrides one.
+    pub fn render_section_placeholder(
+        &mut self,
+        reference: &str,
+        section: &Section,
+        x: i32,
+        y: i32,
+    ) -> &mut Self {
+        let glyphs = self.layout.placeholder(reference, section, x, y);
+        self.draw(&glyphs)
+    }
+
+    /// Renders the form as a one-line road map, e.g. "I V C V C B Cx2 O".
+    pub fn render_road_map(&mut self, form: &Form, x: i32, y: i32) -> &mut Self {
+        let glyph = self.layout.road_map(form, x, y);
+        self.draw(&[glyph])
+    }
+
+    /// A "Capo 3" marker, right-aligned at `x`.
+    pub fn render_capo_marker(&mut self, capo: u32, x: i32, y: i32) -> &mut Self {
+        let glyph = self.layout.capo_marker(capo, x, y);
+        self.draw(&[glyph])
+    }
+
+    pub fn render_repeat(&mut self, repeat_type: &RepeatSign, x: i32, y: i32) -> &mut Self {
+        let kind = GlyphKind::Repeat(repeat_type.clone());
+        self.draw(&[Glyph { x, y, kind }])
+    }
+
     pub fn render_spacer(&mut self, x: i32, y: i32) -> &mut Self {
-        let dot = Circle::new()
-            .set("cx", x)
-            .set("cy", y)
-            .set("r", SPACER_DOT_RADIUS)
-            .set("fill", "black");
+        let kind = GlyphKind::Spacer;
+        self.draw(&[Glyph { x, y, kind }])
+    }
 
-        self.document = self.document.clone().add(dot);
-        self
+    pub fn init_background(&mut self) -> &mut Self {
+        let kind = GlyphKind::Background;
+        self.draw(&[Glyph { x: 0, y: 0, kind }])
     }
 
     pub fn save(&self, path: &str) -> Result<()> {
-        svg::save(path, &self.document).map_err(|e| anyhow::anyhow!("Failed to save SVG: {}", e))
+        svg::save(path, self.backend.document())
+            .map_err(|e| anyhow::anyhow!("Failed to save SVG: {}", e))
     }
 
-    pub fn init_background(&mut self) -> &mut Self {
-        let background = Rectangle::new()
-            .set("width", "100%")
-            .set("height", "100%")
-            .set("fill", "white");
-        self.document = self.document.clone().add(background);
+    /// Rasterizes the chart at `dpi`; at `DEFAULT_DPI` one SVG unit is one
+    /// pixel.
+    pub fn to_image(&self, dpi: f32) -> Result<image::RgbaImage> {
+        if !(dpi > 0.0 && dpi.is_finite()) {
+            anyhow::bail!("DPI must be greater than 0, got: {}", dpi);
+        }
+        let options = usvg::Options {
+            fontdb: system_fonts(),
+            ..usvg::Options::default()
+        };
+        let tree = usvg::Tree::from_str(&self.backend.document().to_string(), &options)
+            .map_err(|e| anyhow::anyhow!("Failed to rasterize SVG: {}", e))?;
+
+        let scale = dpi / DEFAULT_DPI;
+        let size =
+            tree.size().to_int_size().scale_by(scale).ok_or_else(|| {
+                anyhow::anyhow!("Failed to rasterize SVG: {} DPI is too small", dpi)
+            })?;
+        let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height())
+            .ok_or_else(|| anyhow::anyhow!("Failed to rasterize SVG: {} DPI is too large", dpi))?;
+        resvg::render(
+            &tree,
+            tiny_skia::Transform::from_scale(scale, scale),
+            &mut pixmap.as_mut(),
+        );
+
+        let pixels = pixmap
+            .pixels()
+            .iter()
+            .flat_map(|pixel| {
+                let color = pixel.demultiply();
+                [color.red(), color.green(), color.blue(), color.alpha()]
+            })
+            .collect();
+        image::RgbaImage::from_raw(size.width(), size.height(), pixels)
+            .ok_or_else(|| anyhow::anyhow!("Failed to rasterize SVG"))
+    }
+
+    /// Saves the chart as a PNG, JPEG or WebP image, chosen by the file
+    /// extension, at `dpi`.
+    pub fn save_image(&self, path: &str, dpi: f32) -> Result<()> {
+        let format = ImageFormat::from_path(path)
+            .ok_or_else(|| anyhow::anyhow!("Unsupported image format: {}", path))?;
+        let image = self.to_image(dpi)?;
+        let result = match format {
+            ImageFormat::Png => image.save_with_format(path, image::ImageFormat::Png),
+            // JPEG has no alpha channel
+            ImageFormat::Jpeg => image::DynamicImage::ImageRgba8(image)
+                .to_rgb8()
+                .save_with_format(path, image::ImageFormat::Jpeg),
+            ImageFormat::WebP => image.save_with_format(path, image::ImageFormat::WebP),
+        };
+        result.map_err(|e| anyhow::anyhow!("Failed to save image: {}", e))
+    }
+}
+
+/// Draws glyphs as SVG elements, on a canvas sized to fit them.
+pub struct SvgBackend {
+    document: Document,
+    background: bool,
+    bounds: Option<Bounds>,
+    margin: i32,
+}
+
+impl Default for SvgBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SvgBackend {
+    pub fn new() -> Self {
+        let mut backend = Self {
+            document: Document::new(),
+            background: false,
+            bounds: None,
+            margin: DEFAULT_MARGIN,
+        };
+        backend.fit();
+        backend
+    }
+
+    pub fn set_margin(&mut self, margin: i32) -> &mut Self {
+        self.margin = margin.max(0);
+        self.fit();
         self
     }
 
-    fn quality_to_string(&self, quality: &ChordQuality) -> String {
-        match (quality, self.notation_type) {
-            (ChordQuality::Major, _) => String::new(),
-            (ChordQuality::Minor, NotationType::Regular) => "m".to_string(),
-            (ChordQuality::Minor, NotationType::Compact) => "-".to_string(),
-            (ChordQuality::Sus2, _) => "sus2".to_string(),
-            (ChordQuality::Sus4, _) => "sus4".to_string(),
-            (ChordQuality::Aug, NotationType::Regular) => "aug".to_string(),
-            (ChordQuality::Aug, NotationType::Compact) => "+".to_string(),
-            (ChordQuality::Dim, NotationType::Regular) => "dim".to_string(),
-            (ChordQuality::Dim, NotationType::Compact) => "º".to_string(),
+    /// The area drawn on so far, estimated from font sizes for text.
+    pub fn bounds(&self) -> Option<Bounds> {
+        self.bounds
+    }
+
+    /// The content bounds plus margins, or just the margins around the
+    /// origin if nothing is drawn.
+    pub fn view_box(&self) -> Bounds {
+        self.bounds
+            .unwrap_or(Bounds::new(0, 0, 0, 0))
+            .expand(self.margin)
+    }
+
+    /// The drawing as an SVG document, sized to its view box.
+    pub fn document(&self) -> &Document {
+        &self.document
+    }
+
+    /// Sizes the document to its view box, moving the background, which is
+    /// always the first node, to cover it.
+    fn fit(&mut self) {
+        let view_box = self.view_box();
+        self.document.assign("width", view_box.width);
+        self.document.assign("height", view_box.height);
+        self.document.assign(
+            "viewBox",
+            (view_box.x, view_box.y, view_box.width, view_box.height),
+        );
+        if self.background {
+            let background = Rectangle::new()
+                .set("x", view_box.x)
+                .set("y", view_box.y)
+                .set("width", "100%")
+                .set("height", "100%")
+                .set("fill", "white");
+            self.document.get_children_mut()[0] = Box::new(background);
         }
     }
+}
 
-    fn create_chord_group(&self, chord: &Chord, x: i32, y: i32) -> Group {
-        let mut group = Group::new();
+impl RenderBackend for SvgBackend {
+    fn draw(&mut self, glyph: &Glyph) {
+        let (x, y) = (glyph.x, glyph.y);
+        let (node, bounds): (Box<dyn Node>, Bounds) = match &glyph.kind {
+            // Drawn behind everything, sized with the canvas
+            GlyphKind::Background => {
+                if !self.background {
+                    let placeholder = Box::new(Rectangle::new());
+                    self.document.get_children_mut().insert(0, placeholder);
+                    self.background = true;
+                    self.fit();
+                }
+                return;
+            }
+            // Bars are implied by chord spacing
+            GlyphKind::Measure { .. } => return,
+            GlyphKind::Chord { label, shape } => {
+                let mut bounds = text_bounds(label, 20, x - text_width(label, 20) / 2, y);
+                if let Some(shape) = shape {
+                    let width = text_width(shape, CAPO_SHAPE_FONT_SIZE);
+                    let shape_y = y + CAPO_SHAPE_OFFSET;
+                    let shape_bounds =
+                        text_bounds(shape, CAPO_SHAPE_FONT_SIZE, x - width / 2, shape_y);
+                    bounds = bounds.union(&shape_bounds);
+                }
+                let group = chord_group(label, shape.as_deref(), x, y);
+                (Box::new(group), bounds)
+            }
+            GlyphKind::MeasureNumber(number) => {
+                let number = number.to_string();
+                let (left, baseline) = (x - CHORD_SPACING / 2 + 5, y - LINE_HEIGHT * 2 / 5);
+                let text = Text::new()
+                    .set("x", left)
+                    .set("y", baseline)
+                    .set("font-family", FONT_FAMILY)
+                    .set("font-size", MEASURE_NUMBER_FONT_SIZE)
+                    .add(text_node(&number));
+                let size = MEASURE_NUMBER_FONT_SIZE;
+                let bounds = text_bounds(&number, size, left, baseline - size / 2);
+                (Box::new(text), bounds)
+            }
+            GlyphKind::RehearsalMark(mark) => {
+                let width = REHEARSAL_MARK_SIZE.max(12 * mark.chars().count() as i32);
+                let bounds = Bounds::new(
+                    x - CHORD_SPACING / 2 - width / 2,
+                    y - REHEARSAL_MARK_SIZE / 2,
+                    width,
+                    REHEARSAL_MARK_SIZE,
+                );
+                (Box::new(rehearsal_mark(mark, x, y)), bounds)
+            }
+            GlyphKind::Repeat(repeat) => {
+                // Two strokes and the dots on one side of them
+                let dots = REPEAT_DOT_SPACING + 2;
+                let left = match repeat {
+                    RepeatSign::Begin => x - 1,
+                    RepeatSign::End => x - dots,
+                };
+                let width = dots + REPEAT_LINE_SPACING + 1;
+                let bounds = Bounds::new(left, y - LINE_HEIGHT / 3, width, LINE_HEIGHT / 3 * 2);
+                (Box::new(repeat_sign(repeat, x, y)), bounds)
+            }
+            GlyphKind::Spacer => {
+                let dot = Circle::new()
+                    .set("cx", x)
+                    .set("cy", y)
+                    .set("r", SPACER_DOT_RADIUS)
+                    .set("fill", "black");
+                let radius = SPACER_DOT_RADIUS;
+                let bounds = Bounds::new(x - radius, y - radius, 2 * radius, 2 * radius);
+                (Box::new(dot), bounds)
+            }
+            GlyphKind::Annotation { text, kind, width } => {
+                let row_y = match kind {
+                    AnnotationKind::Note => y - ANNOTATION_OFFSET,
+                    AnnotationKind::Lyric => y + ANNOTATION_OFFSET,
+                };
+                // Arial averages a little over half an em per character
+                let max_chars = (width * 2 / ANNOTATION_FONT_SIZE).max(1) as usize;
+                let text = truncate_text(text, max_chars);
+                let left = x - CHORD_SPACING / 3;
+                let bounds = text_bounds(&text, ANNOTATION_FONT_SIZE, left, row_y);
+
+                let mut node = Text::new()
+                    .set("x", left)
+                    .set("y", row_y)
+                    .set("dominant-baseline", "middle")
+                    .set("font-family", FONT_FAMILY)
+                    .set("font-size", ANNOTATION_FONT_SIZE)
+                    .add(text_node(&text));
+                if *kind == AnnotationKind::Lyric {
+                    node = node.set("font-style", "italic");
+                }
+                (Box::new(node), bounds)
+            }
+            GlyphKind::SectionLabel(name) => {
+                let (left, baseline) = (x - CHORD_SPACING / 2, y - LINE_HEIGHT / 2);
+                let label = Text::new()
+                    .set("x", left)
+                    .set("y", baseline)
+                    .set("font-family", FONT_FAMILY)
+                    .set("font-size", 16)
+                    .set("font-weight", "bold")
+                    .add(text_node(name));
+                (Box::new(label), text_bounds(name, 16, left, baseline - 8))
+            }
+            GlyphKind::Placeholder(reference) => {
+                let text = format!("({})", reference);
+                let left = x - CHORD_SPACING / 3;
+                let bounds = text_bounds(&text, 20, left, y);
+                let placeholder = Text::new()
+                    .set("x", left)
+                    .set("y", y)
+                    .set("dominant-baseline", "middle")
+                    .set("font-family", FONT_FAMILY)
+                    .set("font-size", 20)
+                    .set("font-style", "italic")
+                    .add(text_node(&text));
+                (Box::new(placeholder), bounds)
+            }
+            GlyphKind::RoadMap(form) => {
+                let road_map = Text::new()
+                    .set("x", x)
+                    .set("y", y)
+                    .set("dominant-baseline", "middle")
+                    .set("font-family", FONT_FAMILY)
+                    .set("font-size", 16)
+                    .add(text_node(form));
+                (Box::new(road_map), text_bounds(form, 16, x, y))
+            }
+            GlyphKind::CapoMarker(capo) => {
+                let text = format!("Capo {}", capo);
+                let bounds = text_bounds(&text, 16, x - text_width(&text, 16), y);
+                let marker = Text::new()
+                    .set("x", x)
+                    .set("y", y)
+                    .set("text-anchor", "end")
+                    .set("dominant-baseline", "middle")
+                    .set("font-family", FONT_FAMILY)
+                    .set("font-size", 16)
+                    .add(text_node(&text));
+                (Box::new(marker), bounds)
+            }
+        };
+        self.document.append(node);
+        let drawn = match self.bounds {
+            Some(drawn) => drawn.union(&bounds),
+            None => bounds,
+        };
+        if self.bounds != Some(drawn) {
+            self.bounds = Some(drawn);
+            self.fit();
+        }
+    }
+}
+
+/// A rough width for `text` at `font_size`, erring wide so the canvas does
+/// not clip it.
+fn text_width(text: &str, font_size: i32) -> i32 {
+    text.chars().count() as i32 * font_size * 3 / 5
+}
+
+/// The box of one line of text starting at `left` and centered on `y`.
+fn text_bounds(text: &str, font_size: i32, left: i32, y: i32) -> Bounds {
+    let width = text_width(text, font_size);
+    Bounds::new(left, y - font_size * 3 / 5, width, font_size * 6 / 5)
+}
 
-        // Create text element for the chord
-        let chord_text = format!("{}{}", chord.degree, self.quality_to_string(&chord.quality));
-        let text = Text::new()
-            .set("x", x)
-            .set("y", y)
-            .set("text-anchor", "middle")
-            .set("dominant-baseline", "middle")
-            .set("font-family", "Arial")
-            .set("font-size", 20)
-            .add(TextNode::new(chord_text));
+fn chord_group(label: &str, shape: Option<&str>, x: i32, y: i32) -> Group {
+    let text = Text::new()
+        .set("x", x)
+        .set("y", y)
+        .set("text-anchor", "middle")
+        .set("dominant-baseline", "middle")
+        .set("font-family", FONT_FAMILY)
+        .set("font-size", 20)
+        .add(text_node(label));
+    let group = Group::new().add(text);
+
+    // Capo charts show the other set of letter names underneath
+    let Some(shape) = shape else {
+        return group;
+    };
+    let shape_text = Text::new()
+        .set("x", x)
+        .set("y", y + CAPO_SHAPE_OFFSET)
+        .set("text-anchor", "middle")
+        .set("dominant-baseline", "middle")
+        .set("font-family", FONT_FAMILY)
+        .set("font-size", CAPO_SHAPE_FONT_SIZE)
+        .set("fill", "gray")
+        .add(text_node(shape));
+    group.add(shape_text)
+}
 
-        group = group.add(text);
-        group
+/// A boxed rehearsal letter in the gap before the chord at `x`.
+fn rehearsal_mark(mark: &str, x: i32, y: i32) -> Group {
+    let center_x = x - CHORD_SPACING / 2;
+    let width = REHEARSAL_MARK_SIZE.max(12 * mark.chars().count() as i32);
+
+    let border = Rectangle::new()
+        .set("x", center_x - width / 2)
+        .set("y", y - REHEARSAL_MARK_SIZE / 2)
+        .set("width", width)
+        .set("height", REHEARSAL_MARK_SIZE)
+        .set("fill", "white")
+        .set("stroke", "black")
+        .set("stroke-width", 1);
+
+    let text = Text::new()
+        .set("x", center_x)
+        .set("y", y)
+        .set("text-anchor", "middle")
+        .set("dominant-baseline", "middle")
+        .set("font-family", FONT_FAMILY)
+        .set("font-size", 14)
+        .set("font-weight", "bold")
+        .add(text_node(mark));
+
+    Group::new().add(border).add(text)
+}
+
+fn repeat_sign(repeat_type: &RepeatSign, x: i32, y: i32) -> Group {
+    let mut group = Group::new();
+    let center_y = y;
+
+    // Add two vertical lines
+    let line1 = SvgLine::new()
+        .set("x1", x)
+        .set("y1", center_y - LINE_HEIGHT / 3)
+        .set("x2", x)
+        .set("y2", center_y + LINE_HEIGHT / 3)
+        .set("stroke", "black")
+        .set("stroke-width", 2);
+
+    let line2 = SvgLine::new()
+        .set("x1", x + REPEAT_LINE_SPACING)
+        .set("y1", center_y - LINE_HEIGHT / 3)
+        .set("x2", x + REPEAT_LINE_SPACING)
+        .set("y2", center_y + LINE_HEIGHT / 3)
+        .set("stroke", "black")
+        .set("stroke-width", 2);
+
+    group = group.add(line1).add(line2);
+
+    // Add dots based on repeat type
+    let dot_x = match repeat_type {
+        RepeatSign::Begin => x + REPEAT_LINE_SPACING + REPEAT_DOT_SPACING,
+        RepeatSign::End => x - REPEAT_DOT_SPACING,
+    };
+
+    let dot1 = Circle::new()
+        .set("cx", dot_x)
+        .set("cy", center_y - REPEAT_DOT_SPACING)
+        .set("r", 2)
+        .set("fill", "black");
+
+    let dot2 = Circle::new()
+        .set("cx", dot_x)
+        .set("cy", center_y + REPEAT_DOT_SPACING)
+        .set("r", 2)
+        .set("fill", "black");
+
+    group.add(dot1).add(dot2)
+}
+
+/// A text node for `text`, escaped as svg leaves text content as is.
+fn text_node(text: &str) -> TextNode {
+    let escaped = text
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    TextNode::new(escaped)
+}
+
+pub(crate) fn truncate_text(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
     }
+    let mut truncated: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// The system's fonts, loaded once. Generic sans-serif falls back to the
+/// first of a few common faces that is installed.
+pub(crate) fn system_fonts() -> Arc<usvg::fontdb::Database> {
+    static FONTS: OnceLock<Arc<usvg::fontdb::Database>> = OnceLock::new();
+    FONTS
+        .get_or_init(|| {
+            let mut fonts = usvg::fontdb::Database::new();
+            fonts.load_system_fonts();
+            let installed = |family: &str| {
+                fonts
+                    .faces()
+                    .any(|face| face.families.iter().any(|(name, _)| name == family))
+            };
+            let fallback = [
+                "Arial",
+                "Helvetica",
+                "Liberation Sans",
+                "DejaVu Sans",
+                "Noto Sans",
+            ]
+            .into_iter()
+            .find(|family| installed(family))
+            .map(str::to_string)
+            .or_else(|| {
+                let face = fonts.faces().next()?;
+                Some(face.families.first()?.0.clone())
+            });
+            if let Some(family) = fallback {
+                fonts.set_sans_serif_family(family);
+            }
+            Arc::new(fonts)
+        })
+        .clone()
 }
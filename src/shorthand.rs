@@ -0,0 +1,435 @@
+//! A plain-text Nashville shorthand for charts:
+//!
+//! ```text
+//! title: Example
+//! key: G
+//!
+//! [Verse]
+//! ||: 1 4 | 5 1 :||
+//! 6m 4 1_5/7 <4
+//!
+//! [Chorus]
+//! 4 1 5 6m . 4 5 1 1
+//! ```
+//!
+//! Each whitespace-separated chord is a bar of its own; `_` joins chords
+//! into a split bar. `|` bar lines are optional, `||:` and `:||` are repeat
+//! signs, `.` is a spacer, `@A` a rehearsal mark and `<` marks a push.
+//! `[Name]` starts a section; a header naming an earlier section with no
+//! lines of its own repeats it. Chords written without a quality take the
+//! diatonic one, so `2` is minor in a major key. `key:`, `mode:`, `time:`
+//! and the other chart settings go at the top; lines starting with `//` are
+//! comments.
+
+use crate::chart::{Chart, Section};
+use crate::chord::{Accidental, Bass, ChordQuality};
+use crate::line::{ChordData, ChordDef, Line, LineElement, RepeatSign};
+use crate::theory::{MinorNumbering, Mode};
+use anyhow::Result;
+
+/// Quality suffixes, longest first so "min" wins over "m".
+const QUALITY_SUFFIXES: [(&str, ChordQuality); 11] = [
+    ("sus2", ChordQuality::Sus2),
+    ("sus4", ChordQuality::Sus4),
+    ("sus", ChordQuality::Sus4),
+    ("min", ChordQuality::Minor),
+    ("dim", ChordQuality::Dim),
+    ("aug", ChordQuality::Aug),
+    ("m", ChordQuality::Minor),
+    ("-", ChordQuality::Minor),
+    ("°", ChordQuality::Dim),
+    ("º", ChordQuality::Dim),
+    ("+", ChordQuality::Aug),
+];
+
+/// Parses shorthand text into a chart. Qualities left out are empty until
+/// the chart is resolved; use `parser::ShorthandParser` for a validated
+/// chart.
+pub fn parse(text: &str) -> Result<Chart> {
+    let mut chart = Chart::new();
+    let mut sections: Vec<Section> = Vec::new();
+    let mut started = false;
+
+    for (index, raw) in text.lines().enumerate() {
+        let number = index + 1;
+        let trimmed = raw.trim();
+        if trimmed.is_empty() || trimmed.starts_with("//") {
+            continue;
+        }
+
+        if !started {
+            if let Some((name, value)) = directive(trimmed) {
+                set_directive(&mut chart, name, value.trim()).map_err(|message| {
+                    anyhow::anyhow!(error(number, column(raw, name), &message))
+                })?;
+                continue;
+            }
+            started = true;
+        }
+
+        let mut rest = raw;
+        if let Some(header) = trimmed.strip_prefix('[') {
+            let Some((name, after)) = header.split_once(']') else {
+                anyhow::bail!(error(
+                    number,
+                    column(raw, trimmed),
+                    "unclosed section header"
+                ));
+            };
+            if name.trim().is_empty() {
+                anyhow::bail!(error(number, column(raw, trimmed), "empty section header"));
+            }
+            sections.push(Section::new(name.trim()));
+            rest = after;
+        }
+
+        let offset = rest.as_ptr() as usize - raw.as_ptr() as usize;
+        let line = parse_elements(rest)
+            .map_err(|(col, message)| anyhow::anyhow!(error(number, offset + col, &message)))?;
+        if !line.line.is_empty() {
+            if sections.is_empty() {
+                sections.push(Section::new(""));
+            }
+            if let Some(section) = sections.last_mut() {
+                section.add_line(line);
+            }
+        }
+    }
+
+    // A bare header naming an earlier section repeats it
+    for index in 0..sections.len() {
+        let name = sections[index].name.clone();
+        if sections[index].lines.is_empty()
+            && sections[..index]
+                .iter()
+                .any(|s| !s.is_reference() && s.name == name)
+        {
+            sections[index] = Section::reference_to(&name);
+        }
+    }
+
+    chart.sections = sections;
+    Ok(chart)
+}
+
+/// Parses a single line of shorthand, such as `||: 1_4 . 5_1 :||`.
+pub fn parse_line(text: &str) -> Result<Line> {
+    parse_elements(text).map_err(|(col, message)| anyhow::anyhow!(error(1, col, &message)))
+}
+
+/// Writes a chart as shorthand. Chords whose quality is the diatonic one
+/// are written without it. Pickups, annotations and last-measure overrides
+/// have no shorthand and are reported as errors.
+pub fn write(chart: &Chart) -> Result<String> {
+    let mut out = String::new();
+    if let Some(title) = &chart.title {
+        out.push_str(&format!("title: {}\n", title));
+    }
+    if let Some(key) = &chart.key {
+        out.push_str(&format!("key: {}\n", key));
+    }
+    if let Some(mode) = &chart.mode {
+        out.push_str(&format!("mode: {}\n", mode));
+    }
+    if chart.minor_numbering == MinorNumbering::Relative {
+        out.push_str("minor_numbering: relative\n");
+    }
+    if !chart.time.is_common_time() {
+        out.push_str(&format!("time: {}\n", chart.time));
+    }
+    if let Some(tempo) = chart.tempo {
+        out.push_str(&format!("tempo: {}\n", tempo));
+    }
+    if let Some(capo) = chart.capo {
+        out.push_str(&format!("capo: {}\n", capo));
+    }
+
+    let mode = chart.diatonic_mode();
+    for section in &chart.sections {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        if !section.name.is_empty() {
+            out.push_str(&format!("[{}]\n", section.name));
+        }
+        if section.is_reference() {
+            if section.last_measure.is_some() {
+                anyhow::bail!(
+                    "Cannot write \"{}\" as shorthand: last-measure overrides have no shorthand",
+                    section.name
+                );
+            }
+            if section.reference.as_deref() != Some(section.name.as_str()) {
+                anyhow::bail!(
+                    "Cannot write \"{}\" as shorthand: a reference is written as its target's name",
+                    section.name
+                );
+            }
+            continue;
+        }
+        for line in &section.lines {
+            out.push_str(&write_line(line, mode)?);
+            out.push('\n');
+        }
+    }
+    Ok(out)
+}
+
+/// Writes one line as shorthand, leaving out qualities diatonic to `mode`.
+pub fn write_line(line: &Line, mode: Mode) -> Result<String> {
+    let mut tokens = Vec::with_capacity(line.line.len());
+    for element in &line.line {
+        let token = match element {
+            LineElement::Measure {
+                pickup: Some(_), ..
+            } => {
+                anyhow::bail!("Cannot write a pickup measure as shorthand")
+            }
+            LineElement::Measure { measure, .. } => measure
+                .iter()
+                .map(|def| write_chord(&def.chord, mode))
+                .collect::<Vec<_>>()
+                .join("_"),
+            LineElement::Repeat {
+                repeat: RepeatSign::Begin,
+            } => "||:".to_string(),
+            LineElement::Repeat {
+                repeat: RepeatSign::End,
+            } => ":||".to_string(),
+            LineElement::Spacer => ".".to_string(),
+            LineElement::Rehearsal { mark } if !mark.contains(char::is_whitespace) => {
+                format!("@{}", mark)
+            }
+            LineElement::Rehearsal { mark } => {
+                anyhow::bail!("Cannot write rehearsal mark \"{}\" as shorthand", mark)
+            }
+            LineElement::Annotation { .. } => {
+                anyhow::bail!("Cannot write an annotation as shorthand")
+            }
+        };
+        tokens.push(token);
+    }
+    Ok(tokens.join(" "))
+}
+
+fn write_chord(chord: &ChordData, mode: Mode) -> String {
+    let mut out = String::new();
+    if chord.push {
+        out.push('<');
+    }
+    if let Some(accidental) = &chord.accidental {
+        out.push_str(accidental.symbol());
+    }
+    out.push_str(&chord.degree.to_string());
+
+    let extension = chord.extension.as_deref().unwrap_or("");
+    let diatonic = mode.diatonic_quality(chord.degree, chord.accidental);
+    if chord.quality != diatonic.name() {
+        match chord.quality.as_str() {
+            // "maj7" already says major
+            "major" if extension.starts_with("maj") => {}
+            "major" => out.push_str("maj"),
+            // Not yet resolved, so diatonic
+            "" => {}
+            "minor" => out.push_str(ChordQuality::Minor.suffix()),
+            quality => out.push_str(quality),
+        }
+    }
+    out.push_str(extension);
+
+    if let Some(bass) = &chord.bass {
+        out.push_str(&format!("/{}", bass));
+    }
+    out
+}
+
+fn error(line: usize, column: usize, message: &str) -> String {
+    format!(
+        "Failed to parse shorthand at line {}, column {}: {}",
+        line, column, message
+    )
+}
+
+/// 1-based column of `part` within `line`.
+fn column(line: &str, part: &str) -> usize {
+    line.find(part)
+        .map_or(1, |index| line[..index].chars().count() + 1)
+}
+
+/// A `name: value` setting line, as opposed to chords or a header.
+fn directive(line: &str) -> Option<(&str, &str)> {
+    let (name, value) = line.split_once(':')?;
+    let is_name = !name.is_empty() && name.chars().all(|c| c.is_ascii_lowercase() || c == '_');
+    is_name.then_some((name, value))
+}
+
+fn set_directive(chart: &mut Chart, name: &str, value: &str) -> Result<(), String> {
+    match name {
+        "title" => chart.title = Some(value.to_string()),
+        "key" => chart.key = Some(value.parse()?),
+        "mode" => chart.mode = Some(value.parse()?),
+        "time" => chart.time = value.parse()?,
+        "tempo" => {
+            chart.tempo = Some(
+                value
+                    .parse()
+                    .map_err(|_| format!("invalid tempo: {}", value))?,
+            )
+        }
+        "capo" => {
+            chart.capo = Some(
+                value
+                    .parse()
+                    .map_err(|_| format!("invalid capo: {}", value))?,
+            )
+        }
+        "minor_numbering" => {
+            chart.minor_numbering = match value {
+                "tonic" => MinorNumbering::Tonic,
+                "relative" => MinorNumbering::Relative,
+                _ => return Err(format!("invalid minor numbering: {}", value)),
+            }
+        }
+        _ => return Err(format!("unknown setting \"{}\"", name)),
+    }
+    Ok(())
+}
+
+/// Parses the elements of a line, reporting errors with the 1-based column
+/// of the offending token.
+fn parse_elements(text: &str) -> Result<Line, (usize, String)> {
+    let mut line = Line::new();
+    for (start, token) in tokens(text) {
+        let column = text[..start].chars().count() + 1;
+        match token {
+            "|" | "||" => {}
+            "||:" => line.add_element(LineElement::Repeat {
+                repeat: RepeatSign::Begin,
+            }),
+            ":||" => line.add_element(LineElement::Repeat {
+                repeat: RepeatSign::End,
+            }),
+            ":||:" => {
+                line.add_element(LineElement::Repeat {
+                    repeat: RepeatSign::End,
+                });
+                line.add_element(LineElement::Repeat {
+                    repeat: RepeatSign::Begin,
+                });
+            }
+            "." => line.add_element(LineElement::Spacer),
+            _ if token.starts_with('@') => {
+                let mark = &token[1..];
+                if mark.is_empty() {
+                    return Err((column, "empty rehearsal mark".to_string()));
+                }
+                line.add_element(LineElement::Rehearsal {
+                    mark: mark.to_string(),
+                });
+            }
+            _ => {
+                let measure = token
+                    .split('_')
+                    .map(|chord| parse_chord(chord).map(|chord| ChordDef { chord }))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|message| (column, message))?;
+                line.add_element(LineElement::Measure {
+                    measure,
+                    pickup: None,
+                });
+            }
+        }
+    }
+    Ok(line)
+}
+
+/// Whitespace-separated tokens with their byte offsets.
+fn tokens(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split_whitespace()
+        .map(move |token| (token.as_ptr() as usize - text.as_ptr() as usize, token))
+}
+
+/// Parses a chord such as `6m`, `b7`, `<4`, `5sus4`, `2m7` or `1/3`.
+fn parse_chord(token: &str) -> Result<ChordData, String> {
+    let invalid = |reason: &str| format!("invalid chord \"{}\": {}", token, reason);
+    if token.is_empty() {
+        return Err("empty chord in split bar".to_string());
+    }
+
+    let (push, rest) = match token.strip_prefix('<') {
+        Some(rest) => (true, rest),
+        None => (false, token),
+    };
+    let (body, bass) = match rest.split_once('/') {
+        Some((body, bass)) => (body, Some(bass)),
+        None => (rest, None),
+    };
+
+    let (accidental, degree, suffix) =
+        split_degree(body).ok_or_else(|| invalid("expected a degree from 1 to 7"))?;
+
+    let (quality, extension) = parse_suffix(suffix).map_err(|reason| invalid(&reason))?;
+
+    let bass = match bass {
+        Some(bass) => {
+            let (accidental, degree, rest) =
+                split_degree(bass).ok_or_else(|| invalid("expected a bass degree from 1 to 7"))?;
+            if !rest.is_empty() {
+                return Err(invalid("expected a bass degree from 1 to 7"));
+            }
+            Some(Bass { degree, accidental })
+        }
+        None => None,
+    };
+
+    Ok(ChordData {
+        degree,
+        quality: quality.map_or(String::new(), |q| q.name().to_string()),
+        accidental,
+        bass,
+        extension,
+        push,
+    })
+}
+
+/// Splits a chord suffix such as "m7", "sus4" or "maj7" into its quality,
+/// if one is written, and its extension.
+pub(crate) fn parse_suffix(suffix: &str) -> Result<(Option<ChordQuality>, Option<String>), String> {
+    let (quality, extension) = if let Some(extension) = suffix.strip_prefix("maj") {
+        // "maj" on its own forces a major chord; "maj7" is an extension
+        let extension = (!extension.is_empty()).then(|| suffix.to_string());
+        (Some(ChordQuality::Major), extension)
+    } else {
+        let quality = QUALITY_SUFFIXES
+            .iter()
+            .find(|(symbol, _)| suffix.starts_with(symbol));
+        let extension = quality.map_or(suffix, |(symbol, _)| &suffix[symbol.len()..]);
+        (
+            quality.map(|(_, quality)| quality.clone()),
+            (!extension.is_empty()).then(|| extension.to_string()),
+        )
+    };
+    if let Some(extension) = &extension {
+        let valid = extension.chars().next().is_some_and(|c| c.is_ascii_digit())
+            || ["add", "maj"].iter().any(|p| extension.starts_with(p));
+        if !valid {
+            return Err(format!("unknown suffix \"{}\"", extension));
+        }
+    }
+    Ok((quality, extension))
+}
+
+/// Splits an optional accidental and a degree from 1 to 7 off the front of
+/// `text`, returning what follows.
+fn split_degree(text: &str) -> Option<(Option<Accidental>, i32, &str)> {
+    let (accidental, rest) = match text.chars().next()? {
+        'b' => (Some(Accidental::Flat), &text[1..]),
+        '#' => (Some(Accidental::Sharp), &text[1..]),
+        _ => (None, text),
+    };
+    let digit = rest.chars().next()?.to_digit(10)?;
+    if !(1..=7).contains(&digit) {
+        return None;
+    }
+    Some((accidental, digit as i32, &rest[1..]))
+}
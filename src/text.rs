@@ -0,0 +1,293 @@
+//! Plain-text rendering of charts in a monospace font, for terminals, email
+//! and text messages: `| 1  4  | 5  1  :|`.
+
+use crate::chart::Chart;
+use crate::layout::{Glyph, GlyphKind, Layout, RenderBackend};
+use crate::line::{AnnotationKind, Line, RepeatSign};
+use crate::renderer::truncate_text;
+
+/// Columns given to each chord unless its name needs more.
+pub const DEFAULT_COLUMN_WIDTH: usize = 3;
+
+/// Renders lines and charts as text, naming chords the way `layout` does:
+/// the same notation, key and capo shapes, written as "A(G)".
+pub struct TextRenderer<'a> {
+    layout: &'a Layout,
+    column_width: usize,
+}
+
+impl<'a> TextRenderer<'a> {
+    pub fn new(layout: &'a Layout) -> Self {
+        Self {
+            layout,
+            column_width: DEFAULT_COLUMN_WIDTH,
+        }
+    }
+
+    /// Columns for each chord, including the space after it; longer names
+    /// keep one space.
+    pub fn set_column_width(&mut self, width: usize) -> &mut Self {
+        self.column_width = width.max(1);
+        self
+    }
+
+    /// Renders a line as a row of bars, with notes above and lyrics below,
+    /// cut short where they would run into the next chord. The chords of a
+    /// split bar are underlined.
+    pub fn render_line(&self, line: &Line) -> String {
+        self.render(&self.layout.line(line, 0, 0))
+    }
+
+    /// Renders every section of a chart, each under its name after a blank
+    /// line.
+    pub fn render_chart(&self, chart: &Chart) -> String {
+        self.render(&self.layout.chart(chart, 0, 0))
+    }
+
+    fn render(&self, glyphs: &[Glyph]) -> String {
+        let mut backend = TextBackend::new(self.column_width);
+        for glyph in glyphs {
+            backend.draw(glyph);
+        }
+        backend.finish()
+    }
+}
+
+/// Draws glyphs into rows of text. Glyphs sharing a y belong to one line of
+/// the chart; their x only orders them and ties annotations to chords.
+pub struct TextBackend {
+    column_width: usize,
+    text: String,
+    rows: Rows,
+    line_y: Option<i32>,
+    /// Whether the next measure needs a barline before it, and the column
+    /// of the last one written
+    bar_needed: bool,
+    bar_column: usize,
+    /// Whether the last thing written was a measure, to close at line end
+    measure_open: bool,
+    /// Column and chord count of the current measure, and chords written
+    measure: (usize, usize, usize),
+    /// x and column of each chord on the line, for annotations
+    chords: Vec<(i32, usize)>,
+}
+
+/// The rows of text making up one line of a chart.
+#[derive(Default)]
+struct Rows {
+    numbers: String,
+    notes: String,
+    chords: String,
+    underlines: String,
+    lyrics: String,
+    /// The last note and lyric, until the chord after them is drawn
+    note: Option<Overhang>,
+    lyric: Option<Overhang>,
+}
+
+/// An annotation that may run on into the chords after its own: its first
+/// column, its text and the x its chords end at.
+struct Overhang {
+    column: usize,
+    text: String,
+    end: i32,
+}
+
+impl TextBackend {
+    pub fn new(column_width: usize) -> Self {
+        Self {
+            column_width: column_width.max(1),
+            text: String::new(),
+            rows: Rows::default(),
+            line_y: None,
+            bar_needed: true,
+            bar_column: 0,
+            measure_open: false,
+            measure: (0, 0, 0),
+            chords: Vec::new(),
+        }
+    }
+
+    /// Everything drawn so far, one line of text per row.
+    pub fn finish(mut self) -> String {
+        self.end_line();
+        self.text
+    }
+
+    fn start_line(&mut self, y: i32) {
+        if self.line_y != Some(y) {
+            self.end_line();
+            self.line_y = Some(y);
+        }
+    }
+
+    fn end_line(&mut self) {
+        if self.measure_open {
+            self.rows.chords.push('|');
+        }
+        let rows = std::mem::take(&mut self.rows);
+        let rows = [
+            rows.numbers,
+            rows.notes,
+            rows.chords,
+            rows.underlines,
+            rows.lyrics,
+        ];
+        for row in rows.iter().map(|row| row.trim_end()) {
+            if !row.is_empty() {
+                self.text.push_str(row);
+                self.text.push('\n');
+            }
+        }
+        self.line_y = None;
+        self.bar_needed = true;
+        self.bar_column = 0;
+        self.measure_open = false;
+        self.chords.clear();
+    }
+
+    /// Writes a line of its own, such as a section name.
+    fn write_line(&mut self, text: &str) {
+        self.end_line();
+        self.text.push_str(text);
+        self.text.push('\n');
+    }
+}
+
+impl RenderBackend for TextBackend {
+    fn draw(&mut self, glyph: &Glyph) {
+        match &glyph.kind {
+            GlyphKind::Background => return,
+            GlyphKind::RoadMap(form) => return self.write_line(form),
+            GlyphKind::CapoMarker(capo) => return self.write_line(&format!("Capo {}", capo)),
+            GlyphKind::SectionLabel(name) => {
+                self.end_line();
+                if !self.text.is_empty() {
+                    self.text.push('\n');
+                }
+                return self.write_line(name);
+            }
+            _ => self.start_line(glyph.y),
+        }
+
+        let rows = &mut self.rows;
+        let current = width(&rows.chords);
+        match &glyph.kind {
+            GlyphKind::Measure { chords, .. } => {
+                if self.bar_needed {
+                    self.bar_column = current;
+                    rows.chords.push_str("| ");
+                }
+                self.bar_needed = true;
+                self.measure_open = true;
+                self.measure = (width(&rows.chords), *chords, 0);
+            }
+            GlyphKind::Chord { label, shape } => {
+                rows.cut_annotations(glyph.x, current);
+                self.chords.push((glyph.x, current));
+                let name = match shape {
+                    Some(shape) => format!("{}({})", label, shape),
+                    None => label.clone(),
+                };
+                rows.chords
+                    .push_str(&format!("{:<width$} ", name, width = self.column_width - 1));
+
+                let (start, count, written) = &mut self.measure;
+                *written += 1;
+                if *written == *count && *count > 1 {
+                    let end = current + width(&name);
+                    place(&mut rows.underlines, *start, &"-".repeat(end - *start));
+                }
+            }
+            GlyphKind::MeasureNumber(number) => {
+                place(&mut rows.numbers, self.bar_column, &number.to_string());
+            }
+            GlyphKind::RehearsalMark(mark) => {
+                rows.cut_annotations(glyph.x, current);
+                place(&mut rows.notes, current, &format!("[{}]", mark));
+            }
+            GlyphKind::Repeat(repeat) => {
+                let (column, sign) = match repeat {
+                    RepeatSign::Begin => (current, "|: "),
+                    RepeatSign::End => (current + 1, ":| "),
+                };
+                self.bar_column = column;
+                rows.chords.push_str(sign);
+                self.bar_needed = false;
+                self.measure_open = false;
+            }
+            GlyphKind::Spacer => {
+                if self.measure_open {
+                    rows.chords.push('|');
+                }
+                rows.chords.push_str(&" ".repeat(self.column_width + 1));
+                self.bar_needed = true;
+                self.measure_open = false;
+            }
+            GlyphKind::Annotation {
+                text,
+                kind,
+                width: span,
+            } => {
+                let Some(&(_, column)) = self.chords.iter().find(|(x, _)| *x == glyph.x) else {
+                    return;
+                };
+                // Shortened later if it runs into the next chord
+                let (row, overhang) = match kind {
+                    AnnotationKind::Note => (&mut rows.notes, &mut rows.note),
+                    AnnotationKind::Lyric => (&mut rows.lyrics, &mut rows.lyric),
+                };
+                place(row, column, text);
+                *overhang = Some(Overhang {
+                    column: width(row) - width(text),
+                    text: text.clone(),
+                    end: glyph.x + span,
+                });
+            }
+            GlyphKind::Placeholder(reference) => {
+                rows.chords.push_str(&format!("({}) ", reference));
+            }
+            GlyphKind::Background
+            | GlyphKind::RoadMap(_)
+            | GlyphKind::CapoMarker(_)
+            | GlyphKind::SectionLabel(_) => {}
+        }
+    }
+}
+
+impl Rows {
+    /// Shortens any note or lyric whose chords end by `x` so that it stops
+    /// a space short of `column`.
+    fn cut_annotations(&mut self, x: i32, column: usize) {
+        for (row, overhang) in [
+            (&mut self.notes, &mut self.note),
+            (&mut self.lyrics, &mut self.lyric),
+        ] {
+            let Some(annotation) = overhang.take_if(|annotation| annotation.end <= x) else {
+                continue;
+            };
+            if width(row) >= column {
+                let room = column.saturating_sub(annotation.column + 1).max(1);
+                *row = row.chars().take(annotation.column).collect();
+                row.push_str(&truncate_text(&annotation.text, room));
+            }
+        }
+    }
+}
+
+/// Width of a row in characters.
+fn width(row: &str) -> usize {
+    row.chars().count()
+}
+
+/// Writes `text` into `row` from `column`, or one space after what the row
+/// already holds if that reaches `column`.
+fn place(row: &mut String, column: usize, text: &str) {
+    let current = width(row);
+    if current < column {
+        row.push_str(&" ".repeat(column - current));
+    } else if current > 0 {
+        row.push(' ');
+    }
+    row.push_str(text);
+}
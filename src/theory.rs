@@ -0,0 +1,576 @@
+use crate::chord::{Accidental, Bass, Chord, ChordQuality};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Semitones above the tonic for each degree of the major scale.
+pub const MAJOR_SCALE: [i32; 7] = [0, 2, 4, 5, 7, 9, 11];
+
+/// The usual degree for each semitone above the tonic.
+const CHROMATIC_DEGREES: [(i32, Option<Accidental>); 12] = [
+    (1, None),
+    (2, Some(Accidental::Flat)),
+    (2, None),
+    (3, Some(Accidental::Flat)),
+    (3, None),
+    (4, None),
+    (4, Some(Accidental::Sharp)),
+    (5, None),
+    (6, Some(Accidental::Flat)),
+    (6, None),
+    (7, Some(Accidental::Flat)),
+    (7, None),
+];
+
+/// Triad qualities built on each degree of the major scale.
+const MAJOR_QUALITIES: [ChordQuality; 7] = [
+    ChordQuality::Major,
+    ChordQuality::Minor,
+    ChordQuality::Minor,
+    ChordQuality::Major,
+    ChordQuality::Major,
+    ChordQuality::Minor,
+    ChordQuality::Dim,
+];
+
+/// Letter steps and semitones above the root for each chord tone.
+const MAJOR_TRIAD: [(i32, i32); 3] = [(0, 0), (2, 4), (4, 7)];
+const MINOR_TRIAD: [(i32, i32); 3] = [(0, 0), (2, 3), (4, 7)];
+const SUS2_TRIAD: [(i32, i32); 3] = [(0, 0), (1, 2), (4, 7)];
+const SUS4_TRIAD: [(i32, i32); 3] = [(0, 0), (3, 5), (4, 7)];
+const AUG_TRIAD: [(i32, i32); 3] = [(0, 0), (2, 4), (4, 8)];
+const DIM_TRIAD: [(i32, i32); 3] = [(0, 0), (2, 3), (4, 6)];
+
+/// Movable-do syllables for each degree of the major scale, and for the
+/// degree raised and lowered a semitone. Chromatic notes that land on a
+/// diatonic one (#3, b4, #7, b1) take that note's syllable.
+const SOLFEGE: [(&str, &str, &str); 7] = [
+    ("do", "di", "ti"),
+    ("re", "ri", "ra"),
+    ("mi", "fa", "me"),
+    ("fa", "fi", "mi"),
+    ("sol", "si", "se"),
+    ("la", "li", "le"),
+    ("ti", "do", "te"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Letter {
+    C,
+    D,
+    E,
+    F,
+    G,
+    A,
+    B,
+}
+
+/// A spelled note: a letter plus a number of sharps (positive) or flats
+/// (negative).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Note {
+    pub letter: Letter,
+    pub accidental: i32,
+}
+
+/// The seven modes of the major scale. Ionian and aeolian are the usual
+/// major and minor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    #[default]
+    #[serde(alias = "ionian")]
+    Major,
+    Dorian,
+    Phrygian,
+    Lydian,
+    Mixolydian,
+    #[serde(alias = "aeolian")]
+    Minor,
+    Locrian,
+}
+
+/// How a minor-key chart is numbered: from the minor tonic (Am = 1m, so
+/// "1m 4m 5") or from its relative major (Am = 6m, so "6m 2m 3").
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MinorNumbering {
+    #[default]
+    Tonic,
+    Relative,
+}
+
+/// A key in any mode. Only the 15 keys of each mode with at most seven
+/// sharps or flats are accepted, so every diatonic note has a single
+/// accidental at most.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Key {
+    pub tonic: Note,
+    pub mode: Mode,
+}
+
+const LETTERS: [Letter; 7] = [
+    Letter::C,
+    Letter::D,
+    Letter::E,
+    Letter::F,
+    Letter::G,
+    Letter::A,
+    Letter::B,
+];
+
+impl Letter {
+    /// The letter `steps` letters above this one, wrapping from B to C.
+    pub fn offset(&self, steps: i32) -> Letter {
+        LETTERS[(self.index() + steps).rem_euclid(7) as usize]
+    }
+
+    /// 0 for C up to 6 for B.
+    fn index(&self) -> i32 {
+        LETTERS.iter().position(|l| l == self).unwrap_or(0) as i32
+    }
+
+    /// Position on the circle of fifths relative to C, so that a major key's
+    /// signature is this plus seven per sharp on the tonic.
+    fn fifths(&self) -> i32 {
+        match self {
+            Letter::F => -1,
+            Letter::C => 0,
+            Letter::G => 1,
+            Letter::D => 2,
+            Letter::A => 3,
+            Letter::E => 4,
+            Letter::B => 5,
+        }
+    }
+
+    pub fn pitch_class(&self) -> i32 {
+        match self {
+            Letter::C => 0,
+            Letter::D => 2,
+            Letter::E => 4,
+            Letter::F => 5,
+            Letter::G => 7,
+            Letter::A => 9,
+            Letter::B => 11,
+        }
+    }
+
+    fn from_char(c: char) -> Option<Self> {
+        match c.to_ascii_uppercase() {
+            'C' => Some(Letter::C),
+            'D' => Some(Letter::D),
+            'E' => Some(Letter::E),
+            'F' => Some(Letter::F),
+            'G' => Some(Letter::G),
+            'A' => Some(Letter::A),
+            'B' => Some(Letter::B),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Letter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl Note {
+    pub fn new(letter: Letter, accidental: i32) -> Self {
+        Self { letter, accidental }
+    }
+
+    /// 0 for C up to 11 for B.
+    pub fn pitch_class(&self) -> i32 {
+        (self.letter.pitch_class() + self.accidental).rem_euclid(12)
+    }
+
+    /// Spells `pitch_class` on `letter`, with whatever accidental it takes.
+    pub fn on_letter(letter: Letter, pitch_class: i32) -> Self {
+        let accidental = (pitch_class - letter.pitch_class() + 6).rem_euclid(12) - 6;
+        Self { letter, accidental }
+    }
+
+    /// The plainest spelling of a pitch class: a natural if there is one,
+    /// otherwise a single flat or sharp.
+    pub fn from_pitch_class(pitch_class: i32, flats: bool) -> Self {
+        let pitch_class = pitch_class.rem_euclid(12);
+        LETTERS
+            .iter()
+            .map(|&letter| Self::on_letter(letter, pitch_class))
+            .filter(|note| match note.accidental {
+                0 => true,
+                -1 => flats,
+                1 => !flats,
+                _ => false,
+            })
+            .min_by_key(|note| note.accidental.abs())
+            .unwrap_or(Self::new(Letter::C, 0))
+    }
+
+    /// The note `steps` letters and `semitones` above this one, spelled on
+    /// the letter the interval calls for (a third above A is C, not B#).
+    pub fn transpose(&self, steps: i32, semitones: i32) -> Self {
+        Self::on_letter(self.letter.offset(steps), self.pitch_class() + semitones)
+    }
+}
+
+impl fmt::Display for Note {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = if self.accidental < 0 { "b" } else { "#" };
+        write!(
+            f,
+            "{}{}",
+            self.letter,
+            symbol.repeat(self.accidental.unsigned_abs() as usize)
+        )
+    }
+}
+
+impl FromStr for Note {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid note: {}", s);
+        let mut chars = s.trim().chars();
+        let letter = chars
+            .next()
+            .and_then(Letter::from_char)
+            .ok_or_else(invalid)?;
+        let mut accidental = 0;
+        for c in chars {
+            match c {
+                'b' | '♭' => accidental -= 1,
+                '#' | '♯' => accidental += 1,
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(Self { letter, accidental })
+    }
+}
+
+/// The movable-do syllable of a (possibly altered) degree: "sol" for 5,
+/// "te" for b7, "fi" for #4.
+pub fn solfege(degree: i32, accidental: Option<Accidental>) -> &'static str {
+    let (natural, raised, lowered) = SOLFEGE[(degree - 1).rem_euclid(7) as usize];
+    match accidental {
+        None => natural,
+        Some(Accidental::Sharp) => raised,
+        Some(Accidental::Flat) => lowered,
+    }
+}
+
+impl Mode {
+    /// The degree of the major scale this mode starts on, counted from 0.
+    fn rotation(&self) -> usize {
+        match self {
+            Mode::Major => 0,
+            Mode::Dorian => 1,
+            Mode::Phrygian => 2,
+            Mode::Lydian => 3,
+            Mode::Mixolydian => 4,
+            Mode::Minor => 5,
+            Mode::Locrian => 6,
+        }
+    }
+
+    /// Semitones above the tonic for each degree of the mode.
+    pub fn scale(&self) -> [i32; 7] {
+        let start = self.rotation();
+        std::array::from_fn(|i| (MAJOR_SCALE[(start + i) % 7] - MAJOR_SCALE[start]).rem_euclid(12))
+    }
+
+    /// The quality of the diatonic triad on a (possibly altered) degree,
+    /// counted up the major scale from the tonic as chart numbers are. In
+    /// dorian "4" is major and "b3" minor; degrees outside the mode are
+    /// taken as major.
+    pub fn diatonic_quality(&self, degree: i32, accidental: Option<Accidental>) -> ChordQuality {
+        let index = (degree - 1).rem_euclid(7) as usize;
+        let alteration = accidental.as_ref().map_or(0, Accidental::semitones);
+        let pitch = (MAJOR_SCALE[index] + alteration).rem_euclid(12);
+        self.scale()
+            .iter()
+            .position(|&p| p == pitch)
+            .map_or(ChordQuality::Major, |i| {
+                MAJOR_QUALITIES[(self.rotation() + i) % 7].clone()
+            })
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Mode::Major => "major",
+            Mode::Dorian => "dorian",
+            Mode::Phrygian => "phrygian",
+            Mode::Lydian => "lydian",
+            Mode::Mixolydian => "mixolydian",
+            Mode::Minor => "minor",
+            Mode::Locrian => "locrian",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "major" | "ionian" => Ok(Mode::Major),
+            "dorian" => Ok(Mode::Dorian),
+            "phrygian" => Ok(Mode::Phrygian),
+            "lydian" => Ok(Mode::Lydian),
+            "mixolydian" => Ok(Mode::Mixolydian),
+            "minor" | "aeolian" => Ok(Mode::Minor),
+            "locrian" => Ok(Mode::Locrian),
+            _ => Err(format!("invalid mode: {}", s)),
+        }
+    }
+}
+
+impl MinorNumbering {
+    pub fn is_tonic(&self) -> bool {
+        *self == MinorNumbering::Tonic
+    }
+
+    /// Re-numbers a (possibly altered) degree written in this convention into
+    /// the `target` convention, e.g. b3 becomes 1 and 5 becomes 3 going from
+    /// tonic to relative numbering.
+    pub fn convert(
+        &self,
+        target: MinorNumbering,
+        degree: i32,
+        accidental: Option<Accidental>,
+    ) -> (i32, Option<Accidental>) {
+        // The minor tonic is the relative major's sixth degree
+        let (steps, semitones) = match (self, target) {
+            (MinorNumbering::Tonic, MinorNumbering::Relative) => (5, 9),
+            (MinorNumbering::Relative, MinorNumbering::Tonic) => (2, 3),
+            _ => return (degree, accidental),
+        };
+
+        let index = (degree - 1).rem_euclid(7);
+        let alteration = accidental.as_ref().map_or(0, Accidental::semitones);
+        let pitch = MAJOR_SCALE[index as usize] + alteration + semitones;
+
+        let mut new_index = (index + steps).rem_euclid(7);
+        let mut offset = (pitch - MAJOR_SCALE[new_index as usize] + 6).rem_euclid(12) - 6;
+        // Rare chromatic degrees such as #3 land two semitones off the new
+        // degree; move to the neighbouring degree instead
+        if offset.abs() > 1 {
+            new_index = (new_index + offset.signum()).rem_euclid(7);
+            offset = (pitch - MAJOR_SCALE[new_index as usize] + 6).rem_euclid(12) - 6;
+        }
+
+        let accidental = match offset {
+            -1 => Some(Accidental::Flat),
+            1 => Some(Accidental::Sharp),
+            _ => None,
+        };
+        (new_index + 1, accidental)
+    }
+}
+
+impl Key {
+    pub fn new(tonic: Note) -> Self {
+        Self {
+            tonic,
+            mode: Mode::Major,
+        }
+    }
+
+    pub fn minor(tonic: Note) -> Self {
+        Self {
+            tonic,
+            mode: Mode::Minor,
+        }
+    }
+
+    pub fn with_mode(tonic: Note, mode: Mode) -> Self {
+        Self { tonic, mode }
+    }
+
+    /// The major key with the same signature, e.g. C for Am or D dorian.
+    pub fn relative_major(&self) -> Key {
+        match self.mode.rotation() {
+            0 => *self,
+            start => Key::new(
+                self.tonic
+                    .transpose(7 - start as i32, 12 - MAJOR_SCALE[start]),
+            ),
+        }
+    }
+
+    /// The key whose degrees a chart's numbers count from: the relative
+    /// major for a minor key numbered relatively, otherwise the key itself.
+    pub fn numbering_key(&self, numbering: MinorNumbering) -> Key {
+        match numbering {
+            MinorNumbering::Relative => self.relative_major(),
+            MinorNumbering::Tonic => *self,
+        }
+    }
+
+    /// The key `semitones` higher (or lower, if negative) in the same mode,
+    /// spelled with whichever tonic gives the simpler signature. Ties such
+    /// as F#/Gb keep this key's flats or sharps: a whole step below Ab is Gb.
+    pub fn transpose(&self, semitones: i32) -> Key {
+        let pitch_class = self.tonic.pitch_class() + semitones;
+        LETTERS
+            .iter()
+            .map(|&letter| Self::with_mode(Note::on_letter(letter, pitch_class), self.mode))
+            .filter(|key| key.tonic.accidental.abs() <= 1)
+            .min_by_key(|key| {
+                let flats = key.signature() < 0;
+                (key.signature().abs(), flats != self.uses_flats())
+            })
+            .unwrap_or(*self)
+    }
+
+    /// The key a capoed instrument fingers its shapes in, when the chart
+    /// sounds in this key: G with a capo on 2 sounds in A.
+    pub fn capo_shapes(&self, capo: u32) -> Key {
+        self.transpose(-(capo as i32))
+    }
+
+    /// Sharps (positive) or flats (negative) in the key signature.
+    pub fn signature(&self) -> i32 {
+        let tonic = self.relative_major().tonic;
+        tonic.letter.fifths() + 7 * tonic.accidental
+    }
+
+    /// Keys with flats in their signature are spelled with flats.
+    pub fn uses_flats(&self) -> bool {
+        self.signature() < 0
+    }
+
+    /// Spells a (possibly altered) scale degree, counted up the major scale
+    /// from the tonic. The letter comes from the degree, so a b3 in E is G
+    /// and a 7 in C# is B#. Chromatic notes that would need a double
+    /// accidental, or a sharp in a flat key, are respelled enharmonically.
+    pub fn spell(&self, degree: i32, accidental: Option<Accidental>) -> Note {
+        let index = (degree - 1).rem_euclid(7);
+        let alteration = accidental.as_ref().map_or(0, Accidental::semitones);
+        let note = self
+            .tonic
+            .transpose(index, MAJOR_SCALE[index as usize] + alteration);
+
+        let flats = self.uses_flats();
+        if note.accidental.abs() > 1 || (flats && note.accidental > 0) {
+            Note::from_pitch_class(note.pitch_class(), flats)
+        } else {
+            note
+        }
+    }
+
+    /// The (possibly altered) degree a note falls on, the inverse of
+    /// `spell`: Bb is b7 in C and A# is #6. Notes that are more than a
+    /// semitone off their letter's degree, such as E# in Eb, are numbered by
+    /// pitch instead.
+    pub fn degree_of(&self, note: &Note) -> (i32, Option<Accidental>) {
+        let steps = (note.letter.index() - self.tonic.letter.index()).rem_euclid(7);
+        let semitones = (note.pitch_class() - self.tonic.pitch_class()).rem_euclid(12);
+        let offset = (semitones - MAJOR_SCALE[steps as usize] + 6).rem_euclid(12) - 6;
+        match offset {
+            0 => (steps + 1, None),
+            -1 => (steps + 1, Some(Accidental::Flat)),
+            1 => (steps + 1, Some(Accidental::Sharp)),
+            _ => CHROMATIC_DEGREES[semitones as usize],
+        }
+    }
+
+    /// The note name of a (possibly altered) scale degree.
+    pub fn note_name(&self, degree: i32, accidental: Option<Accidental>) -> String {
+        self.spell(degree, accidental).to_string()
+    }
+
+    /// The letter-name symbol of a chord in this key, e.g. "Am7" or "Bb/D".
+    pub fn chord_name(&self, chord: &Chord) -> String {
+        let mut name = self.note_name(chord.degree, chord.accidental);
+        name.push_str(chord.quality.suffix());
+        name.push_str(chord.extension.as_deref().unwrap_or(""));
+        if let Some(bass) = &chord.bass {
+            name.push('/');
+            name.push_str(&self.bass_name(bass));
+        }
+        name
+    }
+
+    pub fn bass_name(&self, bass: &Bass) -> String {
+        self.note_name(bass.degree, bass.accidental)
+    }
+
+    /// The spelled notes of a chord, root first, for note-level output such
+    /// as voicings. Chord tones are spelled from the root, so F#dim gives
+    /// F#, A, C.
+    pub fn chord_tones(&self, chord: &Chord) -> Vec<Note> {
+        let root = self.spell(chord.degree, chord.accidental);
+        let intervals = match chord.quality {
+            ChordQuality::Major => MAJOR_TRIAD,
+            ChordQuality::Minor => MINOR_TRIAD,
+            ChordQuality::Sus2 => SUS2_TRIAD,
+            ChordQuality::Sus4 => SUS4_TRIAD,
+            ChordQuality::Aug => AUG_TRIAD,
+            ChordQuality::Dim => DIM_TRIAD,
+        };
+        intervals
+            .iter()
+            .map(|&(steps, semitones)| root.transpose(steps, semitones))
+            .collect()
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.mode {
+            Mode::Major => write!(f, "{}", self.tonic),
+            Mode::Minor => write!(f, "{}m", self.tonic),
+            mode => write!(f, "{} {}", self.tonic, mode),
+        }
+    }
+}
+
+impl FromStr for Key {
+    type Err = String;
+
+    /// Accepts "G", "Bb", "F#m", "C# minor", "D dorian" and similar.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid key: {}", s);
+        let trimmed = s.trim();
+        let named = trimmed
+            .rsplit_once(char::is_whitespace)
+            .and_then(|(tonic, mode)| Some((tonic, mode.parse().ok()?)));
+        let (tonic, mode) = named.unwrap_or_else(|| {
+            ["minor", "min", "m"]
+                .iter()
+                .find_map(|suffix| trimmed.strip_suffix(suffix))
+                .map_or((trimmed, Mode::Major), |tonic| (tonic, Mode::Minor))
+        });
+        let tonic = tonic.trim().parse().map_err(|_| invalid())?;
+
+        let key = Self { tonic, mode };
+        if key.signature().abs() > 7 {
+            return Err(format!(
+                "invalid key: {} has more than 7 sharps or flats",
+                s
+            ));
+        }
+        Ok(key)
+    }
+}
+
+impl TryFrom<String> for Key {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Key> for String {
+    fn from(key: Key) -> Self {
+        key.to_string()
+    }
+}
@@ -0,0 +1,404 @@
+use anyhow::Result;
+use nns_chart_parser::{
+    chart::{Chart, Section, TimeSignature},
+    chord::Chord,
+    line::{Line, LineElement},
+    parser::ChartParser,
+    renderer::ChordRenderer,
+    theory::{MinorNumbering, Mode},
+};
+use std::fs;
+use std::path::PathBuf;
+
+struct TestDir {
+    path: PathBuf,
+}
+
+impl TestDir {
+    fn new(test_name: &str) -> Self {
+        let path = PathBuf::from(format!("test_output_{}", test_name));
+        if path.exists() {
+            fs::remove_dir_all(&path).unwrap();
+        }
+        fs::create_dir(&path).unwrap();
+        Self { path }
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        if self.path.exists() {
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+}
+
+#[test]
+fn test_chart_add_section() {
+    let mut chart = Chart::new();
+    chart.add_section(Section::with_lines("Verse", vec![Line::new()]));
+    assert_eq!(chart.sections.len(), 1);
+    assert_eq!(chart.sections[0].name, "Verse");
+}
+
+#[test]
+fn test_parse_sectioned_chart() -> Result<()> {
+    let chart = ChartParser::parse_file("tests/fixtures/charts/test_chart.yaml")?;
+    assert_eq!(chart.title.as_deref(), Some("Road Map Example"));
+    assert_eq!(chart.sections.len(), 9);
+    assert_eq!(chart.sections[0].name, "Intro");
+    assert_eq!(chart.sections[1].lines[0].line.len(), 4);
+    Ok(())
+}
+
+#[test]
+fn test_parse_line_file_as_chart() -> Result<()> {
+    let chart = ChartParser::parse_file("tests/fixtures/lines/test_line.yaml")?;
+    assert_eq!(chart.sections.len(), 1);
+    assert!(chart.sections[0].name.is_empty());
+    assert_eq!(chart.sections[0].lines[0].line.len(), 5);
+    Ok(())
+}
+
+#[test]
+fn test_parse_chart_missing_sections() {
+    let result = ChartParser::parse_file("tests/fixtures/triads/test_major.yaml");
+    assert!(result.is_err());
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("missing required fields"));
+}
+
+#[test]
+fn test_render_chart_with_road_map() {
+    let test_dir = TestDir::new("chart_road_map");
+    let output_path = test_dir.path.join("output.svg");
+
+    let chart = ChartParser::parse_file("tests/fixtures/charts/test_chart.yaml").unwrap();
+
+    let mut renderer = ChordRenderer::new();
+    renderer
+        .init_background()
+        .render_road_map(&chart.form(), 50, 30)
+        .render_chart(&chart, 100, 200);
+
+    renderer.save(output_path.to_str().unwrap()).unwrap();
+
+    let content = fs::read_to_string(&output_path).unwrap();
+    assert!(content.contains("I V C V C B Cx2 O"));
+    assert!(content.contains("Bridge"));
+}
+
+fn measure_degrees(element: &LineElement) -> Vec<i32> {
+    match element {
+        LineElement::Measure { measure, .. } => measure.iter().map(|c| c.chord.degree).collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[test]
+fn test_parse_section_references() -> Result<()> {
+    let chart = ChartParser::parse_file("tests/fixtures/charts/test_references.yaml")?;
+    assert_eq!(chart.sections.len(), 5);
+    assert_eq!(chart.form().expanded(), "V C V C C");
+
+    let chorus = &chart.sections[1];
+    let repeat = &chart.sections[3];
+    assert!(!chorus.is_reference());
+    assert!(repeat.is_reference());
+    assert_eq!(repeat.name, "Chorus");
+    assert_eq!(repeat.lines[0].line.len(), chorus.lines[0].line.len());
+    assert_eq!(measure_degrees(&repeat.lines[0].line[2]), vec![1]);
+    Ok(())
+}
+
+#[test]
+fn test_reference_overrides_last_measure() -> Result<()> {
+    let chart = ChartParser::parse_file("tests/fixtures/charts/test_references.yaml")?;
+    let last_chorus = &chart.sections[4].lines[0].line;
+    assert_eq!(measure_degrees(&last_chorus[0]), vec![4]);
+    assert_eq!(measure_degrees(&last_chorus[2]), vec![6]);
+
+    // The definition itself is left untouched
+    assert_eq!(
+        measure_degrees(&chart.sections[1].lines[0].line[2]),
+        vec![1]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_parse_unknown_reference() {
+    let result = ChartParser::parse_file("tests/fixtures/charts/test_unknown_reference.yaml");
+    assert!(result.is_err());
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("Unknown section reference: Bridge"));
+}
+
+#[test]
+fn test_parse_self_reference() {
+    let result = ChartParser::parse_file("tests/fixtures/charts/test_self_reference.yaml");
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "Section \"Chorus\" cannot reference itself"
+    );
+}
+
+#[test]
+fn test_parse_section_needs_lines_or_ref() {
+    let result = ChartParser::parse_file("tests/fixtures/charts/test_empty_section.yaml");
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "Section 2 has neither lines nor a ref"
+    );
+
+    let result = ChartParser::parse_file("tests/fixtures/charts/test_reference_with_lines.yaml");
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "Section \"Chorus\" cannot have both lines and a ref"
+    );
+}
+
+#[test]
+fn test_parse_reference_before_definition() {
+    let result = ChartParser::parse_file("tests/fixtures/charts/test_forward_reference.yaml");
+    assert!(result.is_err());
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("referenced before it is defined"));
+}
+
+#[test]
+fn test_render_references_as_placeholders() {
+    let test_dir = TestDir::new("chart_placeholders");
+    let output_path = test_dir.path.join("output.svg");
+
+    let chart = ChartParser::parse_file("tests/fixtures/charts/test_references.yaml").unwrap();
+
+    let mut renderer = ChordRenderer::new();
+    renderer
+        .set_expand_references(false)
+        .init_background()
+        .render_chart(&chart, 100, 100);
+    renderer.save(output_path.to_str().unwrap()).unwrap();
+
+    let content = fs::read_to_string(&output_path).unwrap();
+    assert!(content.contains("(Verse)"));
+    assert!(content.contains("(Chorus)"));
+    assert!(content.contains("6m")); // Overridden last measure
+}
+
+#[test]
+fn test_parse_includes() -> Result<()> {
+    let chart = ChartParser::parse_file("tests/fixtures/includes/song.yaml")?;
+    assert_eq!(chart.form().expanded(), "I V T O");
+
+    // The intro's own include is resolved relative to the intro file
+    let intro = &chart.sections[0];
+    assert_eq!(intro.lines.len(), 1);
+    assert_eq!(measure_degrees(&intro.lines[0].line[0]), vec![5]);
+
+    let verse = &chart.sections[1];
+    assert_eq!(verse.lines.len(), 2);
+    assert_eq!(measure_degrees(&verse.lines[1].line[0]), vec![5]);
+    Ok(())
+}
+
+#[test]
+fn test_parse_include_cycle() {
+    let result = ChartParser::parse_file("tests/fixtures/includes/cycle_a.yaml");
+    assert!(result.is_err());
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "Include cycle detected: tests/fixtures/includes/cycle_a.yaml -> \
+         tests/fixtures/includes/cycle_b.yaml -> tests/fixtures/includes/cycle_c.yaml -> \
+         tests/fixtures/includes/cycle_a.yaml"
+    );
+}
+
+#[test]
+fn test_parse_missing_include() {
+    let result = ChartParser::parse_file("tests/fixtures/includes/missing_include.yaml");
+    assert!(result.is_err());
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("No such file or directory: tests/fixtures/includes/shared/nonexistent.yaml"));
+}
+
+#[test]
+fn test_parse_include_of_wrong_kind() {
+    let result = ChartParser::parse_file("tests/fixtures/includes/wrong_include.yaml");
+    assert!(result.is_err());
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("does not contain a line"));
+}
+
+#[test]
+fn test_parse_pickup() -> Result<()> {
+    let chart = ChartParser::parse_file("tests/fixtures/charts/test_pickup.yaml")?;
+    assert_eq!(chart.time.to_string(), "3/4");
+    match &chart.sections[0].lines[0].line[0] {
+        LineElement::Measure { pickup, .. } => assert_eq!(*pickup, Some(2)),
+        _ => panic!("expected a measure"),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_parse_pickup_in_wrong_position() {
+    let result = ChartParser::parse_file("tests/fixtures/charts/test_invalid_pickup_position.yaml");
+    assert!(result.is_err());
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("Pickup must be the first measure of a section, found one at measure 3"));
+}
+
+#[test]
+fn test_parse_pickup_longer_than_bar() {
+    let result = ChartParser::parse_file("tests/fixtures/charts/test_invalid_pickup_length.yaml");
+    assert!(result.is_err());
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("Pickup must be between 1 and 2 beats in 3/4, got: 3"));
+}
+
+#[test]
+fn test_parse_pickup_in_single_beat_bar() {
+    let result =
+        ChartParser::parse_file("tests/fixtures/charts/test_invalid_pickup_single_beat.yaml");
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "Pickups are not allowed in 1/4: a bar is a single beat"
+    );
+}
+
+#[test]
+fn test_parse_invalid_time_signature() {
+    let result = ChartParser::parse_file("tests/fixtures/charts/test_invalid_time.yaml");
+    assert!(result.is_err());
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("invalid time signature: 4/3"));
+
+    // Units stop at 32nd notes, as in the schema
+    assert!("4/32".parse::<TimeSignature>().is_ok());
+    assert_eq!(
+        "4/128".parse::<TimeSignature>().unwrap_err(),
+        "invalid time signature: 4/128"
+    );
+}
+
+fn chart_chords(chart: &Chart) -> Vec<String> {
+    chart
+        .sections
+        .iter()
+        .flat_map(|s| &s.lines)
+        .flat_map(|l| &l.line)
+        .filter_map(|element| match element {
+            LineElement::Measure { measure, .. } => Some(measure),
+            _ => None,
+        })
+        .flatten()
+        .map(|def| format!("{:?}", Chord::from(def.chord.clone())))
+        .collect()
+}
+
+fn chart_letters(chart: &Chart) -> Vec<String> {
+    let key = chart.letter_key(None).unwrap();
+    chart
+        .sections
+        .iter()
+        .flat_map(|s| &s.lines)
+        .flat_map(|l| &l.line)
+        .filter_map(|element| match element {
+            LineElement::Measure { measure, .. } => Some(measure),
+            _ => None,
+        })
+        .flatten()
+        .map(|def| key.chord_name(&def.chord.clone().into()))
+        .collect()
+}
+
+#[test]
+fn test_minor_numbering_letter_names() -> Result<()> {
+    let tonic = ChartParser::parse_file("tests/fixtures/minor/test_tonic_numbering.yaml")?;
+    let relative = ChartParser::parse_file("tests/fixtures/minor/test_relative_numbering.yaml")?;
+
+    assert_eq!(tonic.minor_numbering, MinorNumbering::Tonic);
+    assert_eq!(relative.minor_numbering, MinorNumbering::Relative);
+
+    let expected = ["Am", "Dm", "E", "Am", "F", "G", "C/E", "E"];
+    assert_eq!(chart_letters(&tonic), expected);
+    assert_eq!(chart_letters(&relative), expected);
+    Ok(())
+}
+
+#[test]
+fn test_renumber_minor_chart() -> Result<()> {
+    let tonic = ChartParser::parse_file("tests/fixtures/minor/test_tonic_numbering.yaml")?;
+    let relative = ChartParser::parse_file("tests/fixtures/minor/test_relative_numbering.yaml")?;
+
+    let mut converted = tonic.clone();
+    converted.renumber(MinorNumbering::Relative);
+    assert_eq!(converted.minor_numbering, MinorNumbering::Relative);
+    assert_eq!(chart_chords(&converted), chart_chords(&relative));
+
+    converted.renumber(MinorNumbering::Tonic);
+    assert_eq!(chart_chords(&converted), chart_chords(&tonic));
+    Ok(())
+}
+
+#[test]
+fn test_missing_qualities_are_diatonic() -> Result<()> {
+    let chart = ChartParser::parse_file("tests/fixtures/modes/test_diatonic.yaml")?;
+    assert_eq!(chart.mode(), Mode::Major);
+    assert_eq!(
+        chart_letters(&chart),
+        ["G", "Am", "Bm", "C", "D", "Em", "F#dim"]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_modal_chart() -> Result<()> {
+    let chart = ChartParser::parse_file("tests/fixtures/modes/test_dorian.yaml")?;
+    assert_eq!(chart.mode, Some(Mode::Dorian));
+    assert_eq!(chart.mode(), Mode::Dorian);
+    assert_eq!(chart_letters(&chart), ["Dm", "G", "C", "F", "Em", "Am"]);
+    Ok(())
+}
+
+#[test]
+fn test_capo_shapes_key() -> Result<()> {
+    let chart = ChartParser::parse_file("tests/fixtures/charts/test_capo.yaml")?;
+    assert_eq!(chart.capo, Some(2));
+    assert_eq!(chart.shapes_key(None), Some("G".parse().unwrap()));
+    assert_eq!(
+        chart.shapes_key(Some("B".parse().unwrap())),
+        Some("A".parse().unwrap())
+    );
+
+    let mut no_capo = chart.clone();
+    no_capo.capo = None;
+    assert_eq!(no_capo.shapes_key(None), None);
+    Ok(())
+}
+
+#[test]
+fn test_parse_invalid_capo() {
+    let result = ChartParser::parse_file("tests/fixtures/charts/test_invalid_capo.yaml");
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("Capo must be between 1 and 11, got: 12"));
+}
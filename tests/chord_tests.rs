@@ -1,5 +1,4 @@
 use nns_chart_parser::chord::{Chord, ChordQuality};
-use serde_yaml;
 
 #[test]
 fn test_chord_quality_serialization() {
@@ -25,6 +24,7 @@ fn test_chord_serialization() {
     let chord = Chord {
         degree: 1,
         quality: ChordQuality::Major,
+        ..Default::default()
     };
 
     let serialized = serde_yaml::to_string(&chord).unwrap();
@@ -42,10 +42,11 @@ fn test_chord_debug_format() {
     let chord = Chord {
         degree: 1,
         quality: ChordQuality::Major,
+        ..Default::default()
     };
 
     assert_eq!(
         format!("{:?}", chord),
-        "Chord { degree: 1, quality: Major }"
+        "Chord { degree: 1, quality: Major, accidental: None, bass: None, extension: None, push: false }"
     );
 }
@@ -0,0 +1,292 @@
+use anyhow::Result;
+use nns_chart_parser::{
+    chart::Chart,
+    chord::Chord,
+    chordpro,
+    line::{AnnotationKind, LineElement},
+    parser::{ChordProParser, ShorthandParser},
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Each measure of a section as compact numbers, e.g. "1" or "4_6m".
+fn section_measures(chart: &Chart, section: usize) -> Vec<String> {
+    chart.sections[section]
+        .lines
+        .iter()
+        .flat_map(|line| &line.line)
+        .filter_map(|element| match element {
+            LineElement::Measure { measure, .. } => Some(
+                measure
+                    .iter()
+                    .map(|def| Chord::from(def.chord.clone()).to_string())
+                    .collect::<Vec<_>>()
+                    .join("_"),
+            ),
+            LineElement::Spacer => Some(".".to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_import_chordpro() -> Result<()> {
+    let chart = ChordProParser::parse_file("tests/fixtures/chordpro/test_song.cho")?;
+    assert_eq!(chart.title.as_deref(), Some("Example Song"));
+    assert_eq!(chart.key.map(|k| k.to_string()).as_deref(), Some("G"));
+    assert_eq!(chart.form().to_string(), "V C B C");
+
+    assert_eq!(
+        section_measures(&chart, 0),
+        ["1", "4", "1", "5", "1", "6m", "5/7", "57"]
+    );
+    // Bar lines group chords into measures
+    assert_eq!(section_measures(&chart, 1), ["4", "1_6m", "5"]);
+    assert_eq!(section_measures(&chart, 2), ["b3", "b7", ".", "5sus4"]);
+    assert!(chart.sections[3].is_reference());
+    assert_eq!(section_measures(&chart, 3), section_measures(&chart, 1));
+    Ok(())
+}
+
+#[test]
+fn test_import_keeps_lyrics() -> Result<()> {
+    let chart = ChordProParser::parse_file("tests/fixtures/chordpro/test_song.cho")?;
+    let lyrics: Vec<_> = chart.sections[0].lines[1]
+        .line
+        .iter()
+        .filter_map(|element| match element {
+            LineElement::Annotation { annotation } => Some(annotation),
+            _ => None,
+        })
+        .collect();
+    assert!(lyrics.iter().all(|a| a.kind == AnnotationKind::Lyric));
+    let texts: Vec<_> = lyrics.iter().map(|a| a.text.as_str()).collect();
+    assert_eq!(texts, ["That saved a", "wretch like", "me"]);
+    Ok(())
+}
+
+#[test]
+fn test_import_grouped_minor() -> Result<()> {
+    let chart = ChordProParser::parse_file("tests/fixtures/chordpro/test_grouped.cho")?;
+    assert_eq!(section_measures(&chart, 0), ["1m_4m", "57_b6", "b7"]);
+    Ok(())
+}
+
+#[test]
+fn test_import_errors() {
+    let error = |path: &str| ChordProParser::parse_file(path).unwrap_err().to_string();
+    assert_eq!(
+        error("tests/fixtures/chordpro/test_no_key.cho"),
+        "Failed to parse ChordPro at line 2: chords need a {key} directive before them"
+    );
+    assert!(error("tests/fixtures/chordpro/test_invalid_chord.cho")
+        .contains("at line 2: invalid chord \"H7\""));
+}
+
+#[derive(Deserialize)]
+struct Grammar {
+    directives: HashMap<String, String>,
+    chord: ChordGrammar,
+}
+
+#[derive(Deserialize)]
+struct ChordGrammar {
+    letter_roots: Vec<String>,
+    number_roots: Vec<String>,
+    accidentals: Vec<String>,
+    qualities: Vec<String>,
+    extension_characters: String,
+}
+
+impl ChordGrammar {
+    fn is_chord(&self, symbol: &str) -> bool {
+        let (body, bass) = match symbol.split_once('/') {
+            Some((body, bass)) => (body, Some(bass)),
+            None => (symbol, None),
+        };
+        let Some(rest) = self.strip_root(body) else {
+            return false;
+        };
+        let rest = self
+            .qualities
+            .iter()
+            .filter_map(|quality| rest.strip_prefix(quality.as_str()))
+            .min_by_key(|rest| rest.len())
+            .unwrap_or(rest);
+        rest.chars().all(|c| self.extension_characters.contains(c))
+            && bass.is_none_or(|bass| self.strip_root(bass) == Some(""))
+    }
+
+    /// Strips a letter root ("Bb") or a number root ("b7") off `text`.
+    fn strip_root<'a>(&self, text: &'a str) -> Option<&'a str> {
+        let strip_accidental = |text: &'a str| {
+            self.accidentals
+                .iter()
+                .find_map(|accidental| text.strip_prefix(accidental.as_str()))
+                .unwrap_or(text)
+        };
+        if let Some(rest) = self
+            .letter_roots
+            .iter()
+            .find_map(|root| text.strip_prefix(root.as_str()))
+        {
+            return Some(strip_accidental(rest));
+        }
+        self.number_roots
+            .iter()
+            .find_map(|root| strip_accidental(text).strip_prefix(root.as_str()))
+    }
+}
+
+/// Checks every line of `song` against the grammar fixture, returning the
+/// first offending line.
+fn check_grammar(song: &str) -> Result<(), String> {
+    let grammar: Grammar = serde_yaml::from_str(
+        &std::fs::read_to_string("tests/fixtures/chordpro/grammar.yaml").unwrap(),
+    )
+    .unwrap();
+    let mut environment: Option<&str> = None;
+
+    for line in song.lines() {
+        let invalid = || Err(format!("invalid line: {}", line));
+        if let Some(directive) = line.strip_prefix('{').and_then(|l| l.strip_suffix('}')) {
+            let (name, value) = match directive.split_once(": ") {
+                Some((name, value)) => (name, Some(value)),
+                None => (directive, None),
+            };
+            match (grammar.directives.get(name).map(String::as_str), value) {
+                (Some("required"), Some(_)) | (Some("optional" | "none"), None) => {}
+                (Some("optional"), Some(_)) => {}
+                _ => return invalid(),
+            }
+            if let Some(started) = name.strip_prefix("start_of_") {
+                if environment.replace(started).is_some() {
+                    return invalid();
+                }
+            } else if let Some(ended) = name.strip_prefix("end_of_") {
+                if environment.take() != Some(ended) {
+                    return invalid();
+                }
+            }
+            continue;
+        }
+
+        let mut rest = line;
+        while let Some(start) = rest.find('[') {
+            let Some(end) = rest[start..].find(']') else {
+                return invalid();
+            };
+            if !grammar.chord.is_chord(&rest[start + 1..start + end]) {
+                return invalid();
+            }
+            rest = &rest[start + end + 1..];
+        }
+        if rest.contains(']') || line.contains('{') || line.contains('}') {
+            return invalid();
+        }
+    }
+    match environment {
+        Some(open) => Err(format!("unclosed environment: {}", open)),
+        None => Ok(()),
+    }
+}
+
+#[test]
+fn test_export_round_trip() -> Result<()> {
+    let chart = ChordProParser::parse_file("tests/fixtures/chordpro/test_song.cho")?;
+    let song = chordpro::export(&chart, chart.key.as_ref());
+    assert_eq!(check_grammar(&song), Ok(()));
+    assert!(song.starts_with("{title: Example Song}\n{key: G}\n"));
+    assert!(song.contains("[G]Amazing [C]grace how [G]sweet the [D]sound\n"));
+    // Lyrics belong to a whole measure, so they follow its first chord
+    assert!(song.contains("| [C]I once was | [G]lost but now [Em] | [D]found |\n"));
+    assert!(song.contains("\n{start_of_bridge}\n[Bb]Was [F]blind [Dsus4]but now I see\n"));
+    assert!(song.ends_with("\n{chorus}\n"));
+
+    // Spacers are dropped; everything else survives the trip
+    let exported = ChordProParser::parse_str(&song)?;
+    assert_eq!(exported.form().to_string(), chart.form().to_string());
+    for section in 0..chart.sections.len() {
+        let mut measures = section_measures(&chart, section);
+        measures.retain(|measure| measure != ".");
+        assert_eq!(section_measures(&exported, section), measures);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_export_round_trip_minor_with_pushes() -> Result<()> {
+    let chart =
+        ShorthandParser::parse_str("key: Am\nminor_numbering: relative\n\n[Verse]\n6m <4 5 <6m\n")?;
+    let song = chordpro::export(&chart, chart.sounding_key(None).as_ref());
+    assert_eq!(check_grammar(&song), Ok(()));
+    // Pushes have no ChordPro syntax, so they are named in a comment
+    assert_eq!(
+        song,
+        "{key: Am}\n\n{start_of_verse}\n[Am] [F] [G] [Am]\n{comment: Push F, Am}\n{end_of_verse}\n"
+    );
+
+    // Imported songs count from the minor tonic, but sound the same
+    let imported = ChordProParser::parse_str(&song)?;
+    assert_eq!(section_measures(&imported, 0), ["1m", "b6", "b7", "1m"]);
+    Ok(())
+}
+
+#[test]
+fn test_export_modal_key() -> Result<()> {
+    let chart = ShorthandParser::parse_str("key: D dorian\n\n[Verse]\n1m 4 1m 4\n")?;
+    let song = chordpro::export(&chart, chart.sounding_key(None).as_ref());
+    assert_eq!(check_grammar(&song), Ok(()));
+    assert!(song.starts_with("{key: Dm}\n{meta: mode dorian}\n"));
+    assert!(song.contains("[Dm] [G] [Dm] [G]\n"));
+
+    let imported = ChordProParser::parse_str(&song)?;
+    assert_eq!(
+        imported.key.map(|k| k.to_string()).as_deref(),
+        Some("D dorian")
+    );
+    assert_eq!(section_measures(&imported, 0), ["1m", "4", "1m", "4"]);
+    Ok(())
+}
+
+#[test]
+fn test_export_numbers() -> Result<()> {
+    let chart = ShorthandParser::parse_file("tests/fixtures/shorthand/test_song.nns")?;
+    let song = chordpro::export(&chart, None);
+    assert_eq!(check_grammar(&song), Ok(()));
+    assert_eq!(
+        song,
+        "{title: Shorthand Song}
+{time: 3/4}
+
+{start_of_verse: Intro}
+[1] [4] [1] [5]
+{end_of_verse}
+
+{start_of_verse}
+{comment: Rehearsal A}
+{comment: Begin repeat}
+[1] [4] [6m] [5]
+{comment: End repeat}
+| [1] | [4/6] | [2m7] [5] | [4] |
+{comment: Push 4}
+{end_of_verse}
+
+{start_of_chorus}
+| [4] | [1] | [5] | [6m] | [4] | [5sus4] [5] | [1] |
+{end_of_chorus}
+
+{start_of_verse}
+{comment: Rehearsal A}
+{comment: Begin repeat}
+[1] [4] [6m] [5]
+{comment: End repeat}
+| [1] | [4/6] | [2m7] [5] | [4] |
+{comment: Push 4}
+{end_of_verse}
+
+{chorus}
+"
+    );
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use nns_chart_parser::{
+    chart::{Chart, Section},
+    form::{section_abbreviation, Form, FormEntry},
+    line::Line,
+    parser::ChartParser,
+};
+
+fn chart_with_sections(names: &[&str]) -> Chart {
+    Chart::with_sections(
+        names
+            .iter()
+            .map(|name| Section::with_lines(name, vec![Line::new()]))
+            .collect(),
+    )
+}
+
+#[test]
+fn test_section_abbreviation() {
+    assert_eq!(section_abbreviation("Intro"), "I");
+    assert_eq!(section_abbreviation("Verse 2"), "V");
+    assert_eq!(section_abbreviation("pre-chorus"), "PC");
+    assert_eq!(section_abbreviation("CHORUS"), "C");
+    assert_eq!(section_abbreviation("Bridge"), "B");
+    assert_eq!(section_abbreviation("Outro"), "O");
+    assert_eq!(section_abbreviation("Breakdown"), "Bd");
+    assert_eq!(section_abbreviation(""), "");
+}
+
+#[test]
+fn test_form_from_fixture() {
+    let chart = ChartParser::parse_file("tests/fixtures/charts/test_chart.yaml").unwrap();
+    let form = chart.form();
+    assert_eq!(form.expanded(), "I V C V C B C C O");
+    assert_eq!(form.to_string(), "I V C V C B Cx2 O");
+}
+
+#[test]
+fn test_form_collapses_consecutive_sections() {
+    let chart = chart_with_sections(&["Verse 1", "Verse 2", "Chorus", "Chorus", "Chorus"]);
+    assert_eq!(
+        Form::from_chart(&chart).entries,
+        vec![
+            FormEntry {
+                label: "V".to_string(),
+                count: 2
+            },
+            FormEntry {
+                label: "C".to_string(),
+                count: 3
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_form_tells_bridge_from_breakdown() {
+    let chart = chart_with_sections(&["Verse", "Bridge", "Breakdown", "Chorus"]);
+    assert_eq!(Form::from_chart(&chart).to_string(), "V B Bd C");
+}
+
+#[test]
+fn test_form_skips_unnamed_sections() {
+    let chart = ChartParser::parse_file("tests/fixtures/lines/test_line.yaml").unwrap();
+    assert!(chart.form().is_empty());
+    assert_eq!(chart.form().to_string(), "");
+}
@@ -0,0 +1,148 @@
+use anyhow::Result;
+use nns_chart_parser::{
+    format::{chart_to_yaml, format_file},
+    parser::{ChartParser, ShorthandParser},
+};
+use std::fs;
+use std::path::PathBuf;
+
+struct TestDir {
+    path: PathBuf,
+}
+
+impl TestDir {
+    fn new(test_name: &str) -> Self {
+        let path = PathBuf::from(format!("test_output_{}", test_name));
+        if path.exists() {
+            fs::remove_dir_all(&path).unwrap();
+        }
+        fs::create_dir(&path).unwrap();
+        Self { path }
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        if self.path.exists() {
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+}
+
+#[test]
+fn test_format_messy_chart() -> Result<()> {
+    let formatted = format_file("tests/fixtures/format/messy.yaml")?;
+    let canonical = fs::read_to_string("tests/fixtures/format/canonical.yaml")?;
+    assert_eq!(formatted, canonical);
+    assert_eq!(
+        format_file("tests/fixtures/format/canonical.yaml")?,
+        canonical
+    );
+
+    // A parsed chart has every quality resolved, and keeps them all
+    let chart = ChartParser::parse_file("tests/fixtures/format/messy.yaml")?;
+    assert_eq!(chart_to_yaml(&chart)?.matches("quality:").count(), 4);
+    Ok(())
+}
+
+#[test]
+fn test_formatting_preserves_charts() -> Result<()> {
+    let test_dir = TestDir::new("format_fixtures");
+    let fixtures = [
+        "tests/fixtures/charts/test_chart.yaml",
+        "tests/fixtures/charts/test_references.yaml",
+        "tests/fixtures/charts/test_pickup.yaml",
+        "tests/fixtures/charts/test_numbering.yaml",
+        "tests/fixtures/charts/test_capo.yaml",
+        "tests/fixtures/minor/test_relative_numbering.yaml",
+        "tests/fixtures/modes/test_dorian.yaml",
+    ];
+    for fixture in fixtures {
+        let chart = ChartParser::parse_file(fixture)?;
+        let formatted = format_file(fixture)?;
+        // Qualities are kept where written and left out where not
+        let written = fs::read_to_string(fixture)?.matches("quality:").count();
+        assert_eq!(
+            formatted.matches("quality:").count(),
+            written,
+            "{}",
+            fixture
+        );
+
+        let output_path = test_dir.path.join("chart.yaml");
+        fs::write(&output_path, &formatted)?;
+        let reparsed = ChartParser::parse_file(&output_path)?;
+        assert_eq!(
+            serde_yaml::to_value(&reparsed)?,
+            serde_yaml::to_value(&chart)?,
+            "{}",
+            fixture
+        );
+        assert_eq!(format_file(&output_path)?, formatted, "{}", fixture);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_format_line_file() -> Result<()> {
+    let formatted = format_file("tests/fixtures/lines/test_annotations.yaml")?;
+    assert!(formatted.starts_with("---\nline:\n"));
+    Ok(())
+}
+
+#[test]
+fn test_format_shorthand() -> Result<()> {
+    // Without the fixture's comment, which formatting would drop
+    let test_dir = TestDir::new("format_shorthand");
+    let path = test_dir.path.join("song.nns");
+    let text = fs::read_to_string("tests/fixtures/shorthand/test_song.nns")?;
+    fs::write(&path, text.split_once('\n').unwrap().1)?;
+
+    let formatted = format_file(&path)?;
+    assert!(formatted.contains("[Verse]\n@A ||: 1 4 6 5 :||\n"));
+    let original = ShorthandParser::parse_file("tests/fixtures/shorthand/test_song.nns")?;
+    let reparsed = ShorthandParser::parse_str(&formatted)?;
+    assert_eq!(
+        serde_yaml::to_value(&reparsed)?,
+        serde_yaml::to_value(&original)?
+    );
+    Ok(())
+}
+
+#[test]
+fn test_format_refuses_includes() {
+    let result = format_file("tests/fixtures/includes/song.yaml");
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("it includes other files"));
+}
+
+#[test]
+fn test_format_refuses_comments() -> Result<()> {
+    let result = format_file("tests/fixtures/format/comments.yaml");
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "Cannot format tests/fixtures/format/comments.yaml: it has a comment on line 1, \
+         which would be lost"
+    );
+    let result = format_file("tests/fixtures/format/comments.nns");
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("it has a comment on line 5"));
+
+    // One after a value is a comment too, but a `#` inside quotes is not
+    let test_dir = TestDir::new("format_comments");
+    let path = test_dir.path.join("chart.yaml");
+    let yaml = fs::read_to_string("tests/fixtures/format/comments.yaml")?;
+    let yaml = yaml.split_once('\n').unwrap().1;
+    fs::write(&path, yaml)?;
+    assert!(format_file(&path)
+        .unwrap_err()
+        .to_string()
+        .contains("it has a comment on line 4"));
+    fs::write(&path, yaml.replace("  # quiet", ""))?;
+    assert!(format_file(&path)?.contains("title: 'Comments #1'"));
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use anyhow::Result;
+use nns_chart_parser::{
+    layout::{Glyph, GlyphKind, Layout, RenderBackend},
+    parser::ShorthandParser,
+    renderer::{CHORD_SPACING, LINE_HEIGHT, SECTION_LABEL_HEIGHT},
+    shorthand,
+};
+
+/// A backend that keeps the chord names it is given and where.
+#[derive(Default)]
+struct Chords(Vec<(i32, i32, String)>);
+
+impl RenderBackend for Chords {
+    fn draw(&mut self, glyph: &Glyph) {
+        if let GlyphKind::Chord { label, .. } = &glyph.kind {
+            self.0.push((glyph.x, glyph.y, label.clone()));
+        }
+    }
+}
+
+#[test]
+fn test_layout_line_positions() -> Result<()> {
+    let line = shorthand::parse_line("||: 1 4_5 :|| . 6m")?;
+    let glyphs = Layout::new().line(&line, 100, 200);
+
+    let kinds: Vec<_> = glyphs
+        .iter()
+        .map(|glyph| match &glyph.kind {
+            GlyphKind::Repeat(_) => "repeat",
+            GlyphKind::Measure { .. } => "measure",
+            GlyphKind::Chord { .. } => "chord",
+            GlyphKind::Spacer => "spacer",
+            _ => "other",
+        })
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            "repeat", "measure", "chord", "measure", "chord", "chord", "repeat", "spacer",
+            "measure", "chord",
+        ]
+    );
+
+    let mut chords = Chords::default();
+    for glyph in &glyphs {
+        chords.draw(glyph);
+    }
+    let half = CHORD_SPACING / 2;
+    assert_eq!(
+        chords.0,
+        [
+            (100 + half, 200, "1".to_string()),
+            (100 + half + CHORD_SPACING, 200, "4".to_string()),
+            (100 + half + 2 * CHORD_SPACING, 200, "5".to_string()),
+            (100 + 3 * half + 3 * CHORD_SPACING, 200, "6m".to_string()),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_layout_chart_rows() -> Result<()> {
+    let chart = ShorthandParser::parse_str("key: D\n\n[Verse]\n1 4\n5 1\n\n[Chorus]\n4 5\n")?;
+    let mut layout = Layout::new();
+    layout.set_key(chart.key);
+
+    let mut chords = Chords::default();
+    for glyph in layout.chart(&chart, 100, 200) {
+        chords.draw(&glyph);
+    }
+    let rows: Vec<_> = chords.0.iter().map(|(_, y, _)| *y).collect();
+    let chorus = 200 + 2 * LINE_HEIGHT + SECTION_LABEL_HEIGHT;
+    assert_eq!(rows, [200, 200, 300, 300, chorus, chorus]);
+    assert_eq!(chords.0[0].2, "D");
+    Ok(())
+}
@@ -1,5 +1,6 @@
 use nns_chart_parser::{
-    line::{ChordData, ChordDef, Line, LineElement, RepeatSign},
+    line::{AnnotationKind, ChordData, ChordDef, Line, LineElement, RepeatSign},
+    parser::LineParser,
     renderer::ChordRenderer,
 };
 use std::fs;
@@ -41,12 +42,14 @@ fn test_line_with_elements() {
             chord: ChordData {
                 degree: 1,
                 quality: "major".to_string(),
+                ..Default::default()
             },
         },
         ChordDef {
             chord: ChordData {
                 degree: 4,
                 quality: "major".to_string(),
+                ..Default::default()
             },
         },
     ];
@@ -57,6 +60,7 @@ fn test_line_with_elements() {
         },
         LineElement::Measure {
             measure: chord_defs,
+            pickup: None,
         },
         LineElement::Spacer,
         LineElement::Repeat {
@@ -78,12 +82,14 @@ fn test_render_line() {
             chord: ChordData {
                 degree: 1,
                 quality: "major".to_string(),
+                ..Default::default()
             },
         },
         ChordDef {
             chord: ChordData {
                 degree: 4,
                 quality: "major".to_string(),
+                ..Default::default()
             },
         },
     ];
@@ -93,12 +99,14 @@ fn test_render_line() {
             chord: ChordData {
                 degree: 5,
                 quality: "major".to_string(),
+                ..Default::default()
             },
         },
         ChordDef {
             chord: ChordData {
                 degree: 1,
                 quality: "major".to_string(),
+                ..Default::default()
             },
         },
     ];
@@ -109,10 +117,12 @@ fn test_render_line() {
         },
         LineElement::Measure {
             measure: chord_defs1,
+            pickup: None,
         },
         LineElement::Spacer,
         LineElement::Measure {
             measure: chord_defs2,
+            pickup: None,
         },
         LineElement::Repeat {
             repeat: RepeatSign::End,
@@ -133,3 +143,58 @@ fn test_render_line() {
     assert!(content.contains("<circle")); // For spacer and repeat dots
     assert!(content.contains("<line")); // For repeat bars
 }
+
+#[test]
+fn test_parse_annotations() {
+    let line = LineParser::parse_file("tests/fixtures/lines/test_annotations.yaml").unwrap();
+    let annotations: Vec<_> = line
+        .line
+        .iter()
+        .filter_map(|element| match element {
+            LineElement::Annotation { annotation } => Some(annotation),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(annotations.len(), 3);
+    assert_eq!(annotations[0].kind, AnnotationKind::Lyric);
+    assert_eq!(annotations[1].kind, AnnotationKind::Note);
+    assert_eq!(annotations[1].chord, Some(2));
+    assert_eq!(annotations[2].chord, None);
+}
+
+#[test]
+fn test_parse_annotation_with_invalid_chord() {
+    let result = LineParser::parse_file("tests/fixtures/lines/test_invalid_annotation.yaml");
+    assert!(result.is_err());
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("refers to chord 3, but the measure has 1 chord(s)"));
+}
+
+#[test]
+fn test_render_annotations() {
+    let test_dir = TestDir::new("annotations");
+    let output_path = test_dir.path.join("output.svg");
+
+    let line = LineParser::parse_file("tests/fixtures/lines/test_annotations.yaml").unwrap();
+
+    let mut renderer = ChordRenderer::new();
+    renderer
+        .init_background()
+        .render_section_label("Verse & Tag", 100, 200)
+        .render_line(&line, 100, 200);
+    renderer.save(output_path.to_str().unwrap()).unwrap();
+
+    let content = fs::read_to_string(&output_path).unwrap();
+    assert!(content.contains("...mountain high"));
+    // Markup characters in user text are escaped
+    assert!(content.contains("build &amp; swell"));
+    assert!(content.contains("Verse &amp; Tag"));
+    roxmltree::Document::parse(&content).unwrap();
+    assert!(content.contains("font-style=\"italic\""));
+    // Too long for a single measure, so it gets truncated
+    assert!(content.contains("band out, vocal…"));
+    assert!(!content.contains("acoustic guitar only"));
+}
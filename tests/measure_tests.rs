@@ -15,10 +15,12 @@ fn test_measure_with_chords() {
         Chord {
             degree: 1,
             quality: ChordQuality::Major,
+            ..Default::default()
         },
         Chord {
             degree: 4,
             quality: ChordQuality::Major,
+            ..Default::default()
         },
     ];
     let measure = Measure::with_chords(chords.clone());
@@ -36,6 +38,7 @@ fn test_measure_add_chord() {
     let chord = Chord {
         degree: 1,
         quality: ChordQuality::Major,
+        ..Default::default()
     };
     measure.add_chord(chord.clone());
     let measure_chords = measure.get_chords();
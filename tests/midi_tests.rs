@@ -0,0 +1,169 @@
+use anyhow::Result;
+use nns_chart_parser::{chart::TimeSignature, midi, parser::ShorthandParser};
+
+/// A decoded track event: absolute tick, status byte and data.
+#[derive(Debug, PartialEq)]
+struct Event {
+    tick: u32,
+    status: u8,
+    data: Vec<u8>,
+}
+
+fn varlen(bytes: &[u8], at: &mut usize) -> u32 {
+    let mut value = 0;
+    loop {
+        let byte = bytes[*at];
+        *at += 1;
+        value = (value << 7) | u32::from(byte & 0x7F);
+        if byte & 0x80 == 0 {
+            return value;
+        }
+    }
+}
+
+/// Splits a MIDI file into its tracks' events, checking the chunk layout.
+fn read_tracks(bytes: &[u8]) -> Vec<Vec<Event>> {
+    assert_eq!(&bytes[..8], b"MThd\0\0\0\x06");
+    let count = u16::from_be_bytes([bytes[10], bytes[11]]) as usize;
+    let mut at = 14;
+    let mut tracks = Vec::new();
+    for _ in 0..count {
+        assert_eq!(&bytes[at..at + 4], b"MTrk");
+        let length = u32::from_be_bytes(bytes[at + 4..at + 8].try_into().unwrap()) as usize;
+        at += 8;
+        let end = at + length;
+        let mut events = Vec::new();
+        let mut tick = 0;
+        while at < end {
+            tick += varlen(bytes, &mut at);
+            let status = bytes[at];
+            at += 1;
+            let data = match status {
+                0xFF => {
+                    let kind = bytes[at];
+                    at += 1;
+                    let length = varlen(bytes, &mut at) as usize;
+                    at += length;
+                    let mut data = vec![kind];
+                    data.extend_from_slice(&bytes[at - length..at]);
+                    data
+                }
+                0xC0..=0xDF => {
+                    at += 1;
+                    vec![bytes[at - 1]]
+                }
+                _ => {
+                    at += 2;
+                    bytes[at - 2..at].to_vec()
+                }
+            };
+            events.push(Event { tick, status, data });
+        }
+        assert_eq!(at, end);
+        assert_eq!(events.last().unwrap().data, [0x2F]);
+        tracks.push(events);
+    }
+    assert_eq!(at, bytes.len());
+    tracks
+}
+
+/// The notes started in a track as (tick, notes sounding together).
+fn chords(track: &[Event]) -> Vec<(u32, Vec<u8>)> {
+    let mut chords: Vec<(u32, Vec<u8>)> = Vec::new();
+    for event in track.iter().filter(|e| e.status & 0xF0 == 0x90) {
+        match chords.last_mut() {
+            Some((tick, notes)) if *tick == event.tick => notes.push(event.data[0]),
+            _ => chords.push((event.tick, vec![event.data[0]])),
+        }
+    }
+    chords
+}
+
+#[test]
+fn test_midi_header_and_tempo() -> Result<()> {
+    let chart = ShorthandParser::parse_str("title: Waltz\nkey: F\ntime: 3/4\ntempo: 100\n\n1 4\n")?;
+    let bytes = midi::write(&chart, &chart.key.unwrap(), false)?;
+    // Format 1, two tracks, 480 ticks per quarter note
+    assert_eq!(&bytes[8..14], [0, 1, 0, 2, 0x01, 0xE0]);
+
+    let tracks = read_tracks(&bytes);
+    let conductor: Vec<_> = tracks[0].iter().map(|e| e.data.clone()).collect();
+    assert_eq!(
+        conductor,
+        [
+            b"\x03Waltz".to_vec(),
+            // 600000 microseconds per quarter note is 100 bpm
+            vec![0x51, 0x09, 0x27, 0xC0],
+            vec![0x58, 3, 2, 24, 8],
+            // One flat, major
+            vec![0x59, 0xFF, 0],
+            vec![0x2F],
+        ]
+    );
+    assert!(tracks[0].iter().all(|e| e.tick == 0));
+    Ok(())
+}
+
+#[test]
+fn test_midi_block_chords() -> Result<()> {
+    let chart = ShorthandParser::parse_str("key: G\n\n1 4/6 57 2m_5\n")?;
+    let tracks = read_tracks(&midi::write(&chart, &chart.key.unwrap(), true)?);
+    assert_eq!(tracks.len(), 3);
+
+    assert_eq!(
+        chords(&tracks[1]),
+        [
+            (0, vec![55, 59, 62]),
+            (1920, vec![48, 52, 55]),
+            (3840, vec![50, 54, 57, 60]),
+            (5760, vec![57, 60, 64]),
+            (6720, vec![50, 54, 57]),
+        ]
+    );
+    // Roots, or the slash bass, an octave down
+    let bass: Vec<_> = chords(&tracks[2]).into_iter().map(|(_, n)| n[0]).collect();
+    assert_eq!(bass, [43, 40, 38, 45, 38]);
+
+    // The last chord sounds to the end of its bar
+    let last_off = tracks[1].iter().rev().find(|e| e.status == 0x80).unwrap();
+    assert_eq!(last_off.tick, 7680);
+    Ok(())
+}
+
+#[test]
+fn test_midi_plays_repeats() -> Result<()> {
+    let chart = ShorthandParser::parse_str("key: C\n\n[Verse]\n||: 1 4 :|| 5\n6m :||\n")?;
+    let tracks = read_tracks(&midi::write(&chart, &chart.key.unwrap(), true)?);
+    let roots: Vec<_> = chords(&tracks[2]).into_iter().map(|(_, n)| n[0]).collect();
+    // C F C F G, then G Am again from the end of the first repeat
+    assert_eq!(roots, [36, 41, 36, 41, 43, 45, 43, 45]);
+    Ok(())
+}
+
+#[test]
+fn test_midi_time_and_tempo_limits() -> Result<()> {
+    let mut chart = ShorthandParser::parse_str("key: C\ntime: 4/32\n\n1 4\n")?;
+    let key = chart.key.unwrap();
+    let tracks = read_tracks(&midi::write(&chart, &key, false)?);
+    // Four million microseconds per quarter note: 120 32nd notes a minute
+    assert_eq!(tracks[0][0].data, [0x51, 0x3D, 0x09, 0x00]);
+    assert_eq!(tracks[0][1].data, [0x58, 4, 5, 24, 8]);
+
+    // Too slow a quarter note for the tempo event's three bytes
+    chart.time = TimeSignature {
+        beats: 4,
+        unit: 128,
+    };
+    chart.tempo = Some(60);
+    assert_eq!(
+        midi::write(&chart, &key, false).unwrap_err().to_string(),
+        "Tempo 60 in 4/128 is out of MIDI's range"
+    );
+
+    chart.time = "300/4".parse().unwrap();
+    assert_eq!(
+        midi::write(&chart, &key, false).unwrap_err().to_string(),
+        "MIDI allows at most 255 beats to the bar, got: 300/4"
+    );
+    Ok(())
+}
@@ -0,0 +1,189 @@
+use anyhow::Result;
+use nns_chart_parser::{
+    musicxml::{self, Staff},
+    parser::{ChartParser, ShorthandParser},
+    theory::Key,
+};
+use roxmltree::{Document, Node, ParsingOptions};
+
+/// Parses a score, which starts with the MusicXML doctype.
+fn parse(xml: &str) -> Result<Document<'_>> {
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    Ok(Document::parse_with_options(xml, options)?)
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.has_tag_name(name))
+}
+
+fn text<'a>(node: Node<'a, '_>, name: &str) -> &'a str {
+    child(node, name).and_then(|n| n.text()).unwrap_or("")
+}
+
+fn measures<'a, 'input>(doc: &'a Document<'input>) -> Vec<Node<'a, 'input>> {
+    doc.descendants()
+        .filter(|n| n.has_tag_name("measure"))
+        .collect()
+}
+
+#[test]
+fn test_musicxml_structure() -> Result<()> {
+    let chart = ShorthandParser::parse_file("tests/fixtures/shorthand/test_song.nns")?;
+    let key: Key = "G".parse().unwrap();
+    let xml = musicxml::write(&chart, &key, Staff::Slashes);
+    let doc = parse(&xml)?;
+
+    let root = doc.root_element();
+    assert_eq!(root.tag_name().name(), "score-partwise");
+    assert_eq!(root.attribute("version"), Some("4.0"));
+    assert_eq!(
+        text(child(root, "work").unwrap(), "work-title"),
+        "Shorthand Song"
+    );
+    let score_part = root.descendants().find(|n| n.has_tag_name("score-part"));
+    let part = child(root, "part").unwrap();
+    assert_eq!(score_part.unwrap().attribute("id"), part.attribute("id"));
+
+    let measures = measures(&doc);
+    let numbers: Vec<_> = measures
+        .iter()
+        .filter_map(|m| m.attribute("number"))
+        .collect();
+    assert_eq!(numbers.len(), 34);
+    assert_eq!(numbers[..3], ["1", "2", "3"]);
+
+    let attributes = child(measures[0], "attributes").unwrap();
+    assert_eq!(text(attributes, "divisions"), "24");
+    assert_eq!(text(child(attributes, "key").unwrap(), "fifths"), "1");
+    assert_eq!(text(child(attributes, "time").unwrap(), "beats"), "3");
+    assert!(measures[1..]
+        .iter()
+        .all(|m| child(*m, "attributes").is_none()));
+
+    // Every bar of 3/4 is full, with one slash per chord
+    for measure in &measures {
+        let notes: Vec<_> = measure
+            .children()
+            .filter(|n| n.has_tag_name("note"))
+            .collect();
+        let harmonies = measure.children().filter(|n| n.has_tag_name("harmony"));
+        assert_eq!(harmonies.count(), notes.len());
+        let total: u32 = notes
+            .iter()
+            .map(|n| text(*n, "duration").parse::<u32>().unwrap())
+            .sum();
+        assert_eq!(total, 72);
+        assert!(notes.iter().all(|n| text(*n, "notehead") == "slash"));
+    }
+
+    let repeats: Vec<_> = doc
+        .descendants()
+        .filter(|n| n.has_tag_name("repeat"))
+        .map(|n| n.attribute("direction").unwrap())
+        .collect();
+    assert_eq!(repeats, ["forward", "backward", "forward", "backward"]);
+    let words: Vec<_> = doc
+        .descendants()
+        .filter(|n| n.has_tag_name("words"))
+        .filter_map(|n| n.text())
+        .collect();
+    assert_eq!(words, ["Intro", "Verse", "Chorus", "Verse", "Chorus"]);
+    assert_eq!(
+        text(
+            measures[4]
+                .descendants()
+                .find(|n| n.has_tag_name("direction-type"))
+                .unwrap(),
+            "rehearsal"
+        ),
+        "A"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_musicxml_harmony() -> Result<()> {
+    let chart =
+        ShorthandParser::parse_str("key: Bb\n\n1maj7 6m7 4/5 5_57b9 2m7b5 4sus4 b7 1add9\n")?;
+    let key = chart.key.unwrap();
+    let xml = musicxml::write(&chart, &key, Staff::Slashes);
+    let doc = parse(&xml)?;
+
+    let harmonies: Vec<String> = doc
+        .descendants()
+        .filter(|n| n.has_tag_name("harmony"))
+        .map(|harmony| {
+            let root = child(harmony, "root").unwrap();
+            let mut symbol = format!(
+                "{}{} {}",
+                text(root, "root-step"),
+                text(root, "root-alter"),
+                text(harmony, "kind")
+            );
+            if let Some(bass) = child(harmony, "bass") {
+                symbol.push_str(&format!(
+                    " /{}{}",
+                    text(bass, "bass-step"),
+                    text(bass, "bass-alter")
+                ));
+            }
+            for degree in harmony.children().filter(|n| n.has_tag_name("degree")) {
+                symbol.push_str(&format!(
+                    " {}:{}:{}",
+                    text(degree, "degree-type"),
+                    text(degree, "degree-value"),
+                    text(degree, "degree-alter")
+                ));
+            }
+            symbol
+        })
+        .collect();
+    assert_eq!(
+        harmonies,
+        [
+            "B-1 major-seventh",
+            "G minor-seventh",
+            "E-1 major /F",
+            "F major",
+            "F dominant add:9:-1",
+            "C half-diminished",
+            "E-1 suspended-fourth",
+            "A-1 major",
+            "B-1 major add:9:0",
+        ]
+    );
+
+    // The kind's text keeps the written symbol
+    let kind = doc.descendants().find(|n| n.has_tag_name("kind")).unwrap();
+    assert_eq!(kind.attribute("text"), Some("maj7"));
+    // Two chords share a bar of 4/4 equally
+    let split = measures(&doc)[3];
+    let durations: Vec<_> = split
+        .children()
+        .filter(|n| n.has_tag_name("note"))
+        .map(|n| (text(n, "duration"), text(n, "type")))
+        .collect();
+    assert_eq!(durations, [("48", "half"), ("48", "half")]);
+    Ok(())
+}
+
+#[test]
+fn test_musicxml_pickup_and_rests() -> Result<()> {
+    let chart = ChartParser::parse_file("tests/fixtures/charts/test_pickup.yaml")?;
+    let key: Key = "C".parse().unwrap();
+    let xml = musicxml::write(&chart, &key, Staff::Rests);
+    let doc = parse(&xml)?;
+
+    let pickup = measures(&doc)[0];
+    assert_eq!(pickup.attribute("number"), Some("0"));
+    assert_eq!(pickup.attribute("implicit"), Some("yes"));
+    let note = child(pickup, "note").unwrap();
+    assert_eq!(text(note, "duration"), "48");
+    assert!(child(note, "rest").is_some());
+    assert_eq!(measures(&doc)[1].attribute("number"), Some("1"));
+    assert!(doc.descendants().all(|n| !n.has_tag_name("pitch")));
+    Ok(())
+}
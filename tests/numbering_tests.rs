@@ -0,0 +1,127 @@
+use anyhow::Result;
+use nns_chart_parser::{
+    parser::ChartParser,
+    renderer::{ChordRenderer, MeasureNumbering},
+};
+use std::fs;
+use std::path::PathBuf;
+
+struct TestDir {
+    path: PathBuf,
+}
+
+impl TestDir {
+    fn new(test_name: &str) -> Self {
+        let path = PathBuf::from(format!("test_output_{}", test_name));
+        if path.exists() {
+            fs::remove_dir_all(&path).unwrap();
+        }
+        fs::create_dir(&path).unwrap();
+        Self { path }
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        if self.path.exists() {
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+}
+
+#[test]
+fn test_measure_numbers_across_sections() -> Result<()> {
+    let chart = ChartParser::parse_file("tests/fixtures/charts/test_numbering.yaml")?;
+    let numbers = chart.measure_numbers();
+
+    assert_eq!(numbers.total(), 10);
+    // Rehearsal mark and repeat sign are not measures
+    assert_eq!(numbers.get(0, 0, 0), None);
+    assert_eq!(numbers.get(0, 0, 1), None);
+    assert_eq!(numbers.get(0, 0, 2), Some(1));
+    assert_eq!(numbers.get(0, 0, 5), Some(4));
+    assert_eq!(numbers.line_start(0, 1), Some(5));
+    assert_eq!(numbers.line_start(1, 0), Some(7));
+    assert_eq!(numbers.get(1, 0, 4), Some(10));
+    assert_eq!(numbers.get(5, 0, 0), None);
+    Ok(())
+}
+
+#[test]
+fn test_rehearsal_marks() -> Result<()> {
+    let chart = ChartParser::parse_file("tests/fixtures/charts/test_numbering.yaml")?;
+    let numbers = chart.measure_numbers();
+
+    assert_eq!(numbers.rehearsal_mark("A"), Some(1));
+    assert_eq!(numbers.rehearsal_mark("B"), Some(7));
+    assert_eq!(numbers.rehearsal_mark("C"), None);
+    assert_eq!(numbers.rehearsal_marks().len(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_referenced_sections_are_numbered() -> Result<()> {
+    let chart = ChartParser::parse_file("tests/fixtures/charts/test_references.yaml")?;
+    let numbers = chart.measure_numbers();
+
+    // Verse (2) + Chorus (3) + Verse (2) + Chorus (3) + Chorus (3)
+    assert_eq!(numbers.total(), 13);
+    assert_eq!(numbers.line_start(4, 0), Some(11));
+    Ok(())
+}
+
+fn count_measure_numbers(numbering: MeasureNumbering) -> usize {
+    let test_dir = TestDir::new(&format!("numbering_{:?}", numbering));
+    let output_path = test_dir.path.join("output.svg");
+
+    let chart = ChartParser::parse_file("tests/fixtures/charts/test_numbering.yaml").unwrap();
+
+    let mut renderer = ChordRenderer::new();
+    renderer
+        .set_measure_numbering(numbering)
+        .init_background()
+        .render_chart(&chart, 100, 100);
+    renderer.save(output_path.to_str().unwrap()).unwrap();
+
+    let content = fs::read_to_string(&output_path).unwrap();
+    // Background plus the boxes around rehearsal marks A and B
+    assert_eq!(content.matches("<rect").count(), 3);
+    content.matches("font-size=\"10\"").count()
+}
+
+#[test]
+fn test_render_measure_numbers() {
+    assert_eq!(count_measure_numbers(MeasureNumbering::Off), 0);
+    assert_eq!(count_measure_numbers(MeasureNumbering::LineStarts), 3);
+    assert_eq!(count_measure_numbers(MeasureNumbering::EveryBar), 10);
+}
+
+#[test]
+fn test_pickups_are_not_numbered() -> Result<()> {
+    let chart = ChartParser::parse_file("tests/fixtures/charts/test_pickup.yaml")?;
+    let numbers = chart.measure_numbers();
+
+    assert_eq!(numbers.total(), 5);
+    assert_eq!(numbers.get(0, 0, 0), None);
+    assert_eq!(numbers.get(0, 0, 1), Some(1));
+    assert_eq!(numbers.line_start(1, 0), Some(4));
+    Ok(())
+}
+
+#[test]
+fn test_render_pickup_as_partial_bar() {
+    let test_dir = TestDir::new("pickup");
+    let output_path = test_dir.path.join("output.svg");
+
+    let chart = ChartParser::parse_file("tests/fixtures/charts/test_pickup.yaml").unwrap();
+
+    let mut renderer = ChordRenderer::new();
+    renderer.init_background().render_chart(&chart, 100, 100);
+    renderer.save(output_path.to_str().unwrap()).unwrap();
+
+    // A two-beat pickup in 3/4 takes two thirds of a chord's width, so the
+    // first full bar starts 66 units in rather than a full 100
+    let content = fs::read_to_string(&output_path).unwrap();
+    assert!(content.contains("x=\"166\""));
+    assert!(!content.contains("x=\"200\""));
+}
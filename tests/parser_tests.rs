@@ -1,6 +1,6 @@
 use anyhow::Result;
 use nns_chart_parser::{
-    chord::ChordQuality,
+    chord::{Accidental, Bass, ChordQuality},
     parser::{ChordParser, MeasureCollectionParser, MeasureParser},
 };
 
@@ -40,9 +40,37 @@ fn test_parse_all_qualities() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_parse_missing_quality_is_diatonic() -> Result<()> {
+    let test_files = [
+        (
+            "tests/fixtures/inferred/degree_1.yaml",
+            1,
+            ChordQuality::Major,
+        ),
+        (
+            "tests/fixtures/inferred/degree_2.yaml",
+            2,
+            ChordQuality::Minor,
+        ),
+        (
+            "tests/fixtures/inferred/degree_7.yaml",
+            7,
+            ChordQuality::Dim,
+        ),
+    ];
+
+    for (file_path, degree, expected_quality) in test_files {
+        let chord = ChordParser::parse_file(file_path)?;
+        assert_eq!(chord.degree, degree);
+        assert_eq!(chord.quality, expected_quality);
+    }
+    Ok(())
+}
+
 #[test]
 fn test_parse_missing_fields() {
-    let result = ChordParser::parse_file("tests/fixtures/invalid_chords/missing_fields.yaml");
+    let result = ChordParser::parse_file("tests/fixtures/invalid_chords/missing_degree.yaml");
     assert!(result.is_err());
     assert!(result
         .unwrap_err()
@@ -157,3 +185,32 @@ fn test_parse_nonexistent_multiple_measures() {
         .to_string()
         .contains("No such file or directory"));
 }
+
+#[test]
+fn test_parse_slash_chord() -> Result<()> {
+    let chord = ChordParser::parse_file("tests/fixtures/slash/test_slash.yaml")?;
+    assert_eq!(chord.degree, 4);
+    assert_eq!(chord.accidental, None);
+    assert_eq!(chord.bass, Some(Bass::new(6)));
+
+    let chord = ChordParser::parse_file("tests/fixtures/slash/test_flat_slash.yaml")?;
+    assert_eq!(chord.accidental, Some(Accidental::Flat));
+    assert_eq!(
+        chord.bass,
+        Some(Bass {
+            degree: 3,
+            accidental: Some(Accidental::Flat)
+        })
+    );
+    Ok(())
+}
+
+#[test]
+fn test_parse_invalid_bass() {
+    let result = ChordParser::parse_file("tests/fixtures/slash/test_invalid_bass.yaml");
+    assert!(result.is_err());
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("invalid bass degree: x3"));
+}
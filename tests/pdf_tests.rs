@@ -0,0 +1,123 @@
+use anyhow::Result;
+use nns_chart_parser::{
+    layout::Layout,
+    parser::ShorthandParser,
+    pdf::{self, PaperSize},
+};
+use printpdf::lopdf::{content::Content, Document, Object};
+
+/// A chart of `sections` eight-line verses, each line four bars long.
+fn long_chart(sections: usize) -> String {
+    let mut source = String::from("title: Long Song\nkey: D\n");
+    for section in 1..=sections {
+        source.push_str(&format!("\n[Verse {}]\n", section));
+        for _ in 0..8 {
+            source.push_str("1 4 6m 5\n");
+        }
+    }
+    source
+}
+
+#[test]
+fn test_pdf_pages_and_fonts() -> Result<()> {
+    let chart = ShorthandParser::parse_file("tests/fixtures/shorthand/test_song.nns")?;
+    let bytes = pdf::write(&chart, &Layout::new(), PaperSize::A4)?;
+    let document = Document::load_mem(&bytes)?;
+    assert_eq!(document.get_pages().len(), 1);
+
+    let page = *document.get_pages().values().next().unwrap();
+    let media_box = document
+        .get_dictionary(page)?
+        .get(b"MediaBox")?
+        .as_array()?;
+    let size: Vec<f32> = media_box
+        .iter()
+        .map(|n| n.as_float().unwrap_or_else(|_| n.as_i64().unwrap() as f32))
+        .collect();
+    assert_eq!(size[2].round(), 595.0);
+    assert_eq!(size[3].round(), 842.0);
+
+    // Every font carries its TrueType program
+    let descriptors: Vec<_> = document
+        .objects
+        .values()
+        .filter_map(|object| match object {
+            Object::Dictionary(dict) if dict.has(b"FontName") => Some(dict),
+            _ => None,
+        })
+        .collect();
+    assert!(!descriptors.is_empty());
+    assert!(descriptors.iter().all(|dict| dict.has(b"FontFile2")));
+    Ok(())
+}
+
+#[test]
+fn test_pdf_breaks_between_sections() -> Result<()> {
+    let chart = ShorthandParser::parse_str(&long_chart(6))?;
+    let layout = Layout::new();
+    let pages = pdf::paginate(&chart, &layout, PaperSize::Letter);
+    assert!(pages.len() > 1);
+
+    // Each section fits on a page, so none is split
+    let runs: Vec<_> = pages.iter().flatten().collect();
+    assert_eq!(runs.len(), 6);
+    for (index, run) in runs.iter().enumerate() {
+        assert_eq!(run.section, index);
+        assert_eq!(run.lines, 0..8);
+    }
+
+    let bytes = pdf::write(&chart, &layout, PaperSize::Letter)?;
+    assert_eq!(Document::load_mem(&bytes)?.get_pages().len(), pages.len());
+    Ok(())
+}
+
+#[test]
+fn test_pdf_splits_long_section_between_lines() -> Result<()> {
+    let mut source = String::from("key: C\n\n[Intro]\n1 5\n\n[Verse]\n");
+    for _ in 0..20 {
+        source.push_str("1 4 6m 5\n");
+    }
+    let chart = ShorthandParser::parse_str(&source)?;
+    let pages = pdf::paginate(&chart, &Layout::new(), PaperSize::Letter);
+    assert_eq!(pages.len(), 2);
+
+    // The verse starts under the intro and carries on over the page
+    let verse: Vec<_> = pages
+        .iter()
+        .flatten()
+        .filter(|run| run.section == 1)
+        .collect();
+    assert_eq!(verse.len(), 2);
+    assert_eq!(verse[0].lines.start, 0);
+    assert_eq!(verse[0].lines.end, verse[1].lines.start);
+    assert_eq!(verse[1].lines.end, 20);
+    Ok(())
+}
+
+#[test]
+fn test_pdf_shrinks_text_with_long_lines() -> Result<()> {
+    let mut source = String::from("key: C\n\n[Verse]\n");
+    source.push_str(&vec!["6m7/b3"; 16].join(" "));
+    let chart = ShorthandParser::parse_str(&source)?;
+    let layout = Layout::new();
+    assert_eq!(pdf::paginate(&chart, &layout, PaperSize::Letter).len(), 1);
+
+    let bytes = pdf::write(&chart, &layout, PaperSize::Letter)?;
+    let document = Document::load_mem(&bytes)?;
+    let page = *document.get_pages().values().next().unwrap();
+    let content = Content::decode(&document.get_page_content(page)?)?;
+    let sizes: Vec<f32> = content
+        .operations
+        .iter()
+        .filter(|operation| operation.operator == "Tf")
+        .filter_map(|operation| operation.operands.get(1)?.as_float().ok())
+        .collect();
+
+    // After the header and section label come the sixteen chords, set
+    // smaller than the 14pt of a line that fits the page
+    let chords = &sizes[sizes.len() - 16..];
+    assert_eq!(sizes.len(), 20);
+    assert!(chords.iter().all(|&size| size == chords[0]));
+    assert!(chords[0] > 0.0 && chords[0] < 10.0);
+    Ok(())
+}
@@ -1,8 +1,12 @@
 use nns_chart_parser::{
     chord::{Chord, ChordQuality},
     measure::Measure,
-    parser::ChordParser,
-    renderer::{ChordRenderer, SVG_HEIGHT, SVG_WIDTH},
+    parser::{ChartParser, ChordParser, ShorthandParser},
+    renderer::{
+        Bounds, CapoDisplay, ChordRenderer, ImageFormat, NotationType, CHORD_SPACING, DEFAULT_DPI,
+        DEFAULT_MARGIN,
+    },
+    shorthand,
 };
 use std::fs;
 use std::path::PathBuf;
@@ -113,7 +117,11 @@ fn test_render_at_boundaries() {
     renderer
         .init_background()
         .render_chord(&chord, 0, 0) // Top-left
-        .render_chord(&chord, SVG_WIDTH, SVG_HEIGHT); // Bottom-right
+        .render_chord(&chord, 800, 400); // Bottom-right
+
+    let view_box = renderer.view_box();
+    assert!(view_box.x < 0 && view_box.y < 0);
+    assert!(view_box.right() > 800 && view_box.bottom() > 400);
 
     renderer.save(output_path.to_str().unwrap()).unwrap();
     assert!(output_path.exists());
@@ -139,14 +147,17 @@ fn test_render_measure() {
         Chord {
             degree: 1,
             quality: ChordQuality::Major,
+            ..Default::default()
         },
         Chord {
             degree: 4,
             quality: ChordQuality::Major,
+            ..Default::default()
         },
         Chord {
             degree: 5,
             quality: ChordQuality::Major,
+            ..Default::default()
         },
     ];
     let measure = Measure::with_chords(chords);
@@ -164,3 +175,231 @@ fn test_render_measure() {
     assert!(content.contains("4")); // Second chord
     assert!(content.contains("5")); // Third chord
 }
+
+#[test]
+fn test_render_letter_names() {
+    let test_dir = TestDir::new("letter_names");
+    let output_path = test_dir.path.join("output.svg");
+
+    let chart = ChartParser::parse_file("tests/fixtures/charts/test_key.yaml").unwrap();
+
+    let mut renderer = ChordRenderer::new();
+    renderer
+        .set_key(chart.key)
+        .init_background()
+        .render_chart(&chart, 100, 200);
+    renderer.save(output_path.to_str().unwrap()).unwrap();
+
+    let content = fs::read_to_string(&output_path).unwrap();
+    assert!(content.contains("Bb/D"));
+    assert!(content.contains("Eb"));
+    assert!(content.contains("Gm"));
+}
+
+#[test]
+fn test_render_altered_numbers() {
+    let test_dir = TestDir::new("altered_numbers");
+    let output_path = test_dir.path.join("output.svg");
+
+    let chart = ChartParser::parse_file("tests/fixtures/charts/test_key.yaml").unwrap();
+
+    let mut renderer = ChordRenderer::new();
+    renderer.init_background().render_chart(&chart, 100, 200);
+    renderer.save(output_path.to_str().unwrap()).unwrap();
+
+    let content = fs::read_to_string(&output_path).unwrap();
+    assert!(content.contains("4/6"));
+    assert!(content.contains("b7"));
+    assert!(!content.contains("Bb"));
+}
+
+#[test]
+fn test_render_capo_shapes() {
+    let test_dir = TestDir::new("capo_shapes");
+    let chart = ChartParser::parse_file("tests/fixtures/charts/test_capo.yaml").unwrap();
+
+    let render = |display: CapoDisplay, name: &str| -> String {
+        let output_path = test_dir.path.join(name);
+        let mut renderer = ChordRenderer::new();
+        renderer
+            .set_key(chart.letter_key(None))
+            .set_capo_shapes(chart.shapes_key(None))
+            .set_capo_display(display)
+            .init_background()
+            .render_chart(&chart, 100, 200);
+        // Right-aligned over the chart, as the command line places it
+        let right = renderer.bounds().unwrap().right();
+        renderer.render_capo_marker(chart.capo.unwrap(), right, 30);
+        renderer.save(output_path.to_str().unwrap()).unwrap();
+        fs::read_to_string(&output_path).unwrap()
+    };
+
+    let sounding_above = render(CapoDisplay::SoundingAbove, "sounding.svg");
+    assert!(sounding_above.contains("Capo 2"));
+    assert!(sounding_above.contains("F#m"));
+    assert!(sounding_above.find("F#m") < sounding_above.find("Em"));
+
+    let shapes_above = render(CapoDisplay::ShapesAbove, "shapes.svg");
+    assert!(shapes_above.find("Em") < shapes_above.find("F#m"));
+}
+
+#[test]
+fn test_render_roman_numerals() {
+    let test_dir = TestDir::new("roman_numerals");
+    let chart = ChartParser::parse_file("tests/fixtures/charts/test_roman.yaml").unwrap();
+
+    let render = |key, name: &str| -> String {
+        let output_path = test_dir.path.join(name);
+        let mut renderer = ChordRenderer::with_notation(NotationType::Roman);
+        renderer
+            .set_key(key)
+            .init_background()
+            .render_chart(&chart, 100, 200);
+        renderer.save(output_path.to_str().unwrap()).unwrap();
+        fs::read_to_string(&output_path).unwrap()
+    };
+
+    let content = render(None, "numerals.svg");
+    assert_eq!(
+        text_contents(&content),
+        ["Verse", "I", "vi", "IV", "V7", "vii°", "bVII", "V+", "ii7/IV"]
+    );
+
+    // Letter names keep their usual suffixes
+    let content = render(chart.key, "letters.svg");
+    assert_eq!(
+        text_contents(&content),
+        ["Verse", "G", "Em", "C", "D7", "F#dim", "F", "Daug", "Am7/C"]
+    );
+}
+
+/// The text of every `<text>` element in an SVG document, in order.
+fn text_contents(svg: &str) -> Vec<String> {
+    roxmltree::Document::parse(svg)
+        .unwrap()
+        .descendants()
+        .filter(|node| node.has_tag_name("text"))
+        .filter_map(|node| node.text().map(|text| text.trim().to_string()))
+        .collect()
+}
+
+#[test]
+fn test_render_solfege() {
+    let test_dir = TestDir::new("solfege");
+    let output_path = test_dir.path.join("output.svg");
+
+    let chart = ChartParser::parse_file("tests/fixtures/charts/test_roman.yaml").unwrap();
+
+    let mut renderer = ChordRenderer::with_notation(NotationType::Solfege);
+    renderer.init_background().render_chart(&chart, 100, 200);
+    renderer.save(output_path.to_str().unwrap()).unwrap();
+
+    let content = fs::read_to_string(&output_path).unwrap();
+    assert_eq!(
+        text_contents(&content),
+        ["Verse", "do", "lam", "fa", "sol7", "tidim", "te", "solaug", "rem7/fa"]
+    );
+}
+
+#[test]
+fn test_render_pushes() {
+    let test_dir = TestDir::new("pushes");
+    let output_path = test_dir.path.join("output.svg");
+
+    let chart = ShorthandParser::parse_str("[Verse]\n<4 1 5\n").unwrap();
+    let mut renderer = ChordRenderer::new();
+    renderer.init_background().render_chart(&chart, 100, 200);
+    renderer.save(output_path.to_str().unwrap()).unwrap();
+
+    // The push marker is escaped, so the output stays well-formed
+    let content = fs::read_to_string(&output_path).unwrap();
+    assert_eq!(text_contents(&content), ["Verse", "<4", "1", "5"]);
+    assert!(renderer.to_image(DEFAULT_DPI).is_ok());
+}
+
+#[test]
+fn test_rasterize_chart() {
+    let chord = ChordParser::parse_file("tests/fixtures/triads/test_major.yaml").unwrap();
+    let mut renderer = ChordRenderer::new();
+    renderer.init_background().render_chord(&chord, 400, 200);
+
+    let view_box = renderer.view_box();
+    let (width, height) = (view_box.width as u32, view_box.height as u32);
+    let image = renderer.to_image(DEFAULT_DPI).unwrap();
+    assert_eq!(image.dimensions(), (width, height));
+    assert_eq!(image.get_pixel(0, 0).0, [255, 255, 255, 255]);
+    // The chord's text is drawn around its position, inside the margins
+    let (x, y) = ((400 - view_box.x) as u32, (200 - view_box.y) as u32);
+    let inked = (x - 10..x + 10)
+        .flat_map(|x| (y - 10..y + 10).map(move |y| (x, y)))
+        .any(|(x, y)| image.get_pixel(x, y).0[0] < 128);
+    assert!(inked);
+
+    let image = renderer.to_image(2.0 * DEFAULT_DPI).unwrap();
+    assert_eq!(image.dimensions(), (2 * width, 2 * height));
+    assert_eq!(
+        renderer.to_image(0.0).unwrap_err().to_string(),
+        "DPI must be greater than 0, got: 0"
+    );
+}
+
+#[test]
+fn test_save_images() {
+    let test_dir = TestDir::new("images");
+    let chord = ChordParser::parse_file("tests/fixtures/triads/test_major.yaml").unwrap();
+    let mut renderer = ChordRenderer::new();
+    renderer.init_background().render_chord(&chord, 400, 200);
+    let view_box = renderer.view_box();
+
+    for name in ["chart.png", "chart.jpg", "chart.webp"] {
+        let path = test_dir.path.join(name);
+        let path = path.to_str().unwrap();
+        assert!(ImageFormat::from_path(path).is_some());
+        renderer.save_image(path, 48.0).unwrap();
+        let image = image::open(path).unwrap();
+        assert_eq!(
+            (image.width(), image.height()),
+            (view_box.width as u32 / 2, view_box.height as u32 / 2)
+        );
+    }
+
+    let error = renderer.save_image("chart.gif", DEFAULT_DPI).unwrap_err();
+    assert_eq!(error.to_string(), "Unsupported image format: chart.gif");
+}
+
+#[test]
+fn test_canvas_fits_content() {
+    let test_dir = TestDir::new("canvas");
+    let output_path = test_dir.path.join("output.svg");
+
+    // Longer than the old fixed 800-unit canvas
+    let line = shorthand::parse_line("1 2m 3m 4 5 6m 7dim 1 2m 3m 4 5").unwrap();
+    let mut renderer = ChordRenderer::new();
+    renderer.init_background().render_line(&line, 100, 200);
+
+    let bounds = renderer.bounds().unwrap();
+    assert!(bounds.x < 100 && bounds.right() > 100 + 11 * CHORD_SPACING);
+    assert!(bounds.y < 200 && bounds.bottom() > 200);
+    assert_eq!(renderer.view_box(), bounds.expand(DEFAULT_MARGIN));
+
+    renderer.set_margin(0);
+    assert_eq!(renderer.view_box(), bounds);
+    renderer.save(output_path.to_str().unwrap()).unwrap();
+    let content = fs::read_to_string(&output_path).unwrap();
+    let Bounds {
+        x,
+        y,
+        width,
+        height,
+    } = bounds;
+    assert!(content.contains(&format!(
+        r#"height="{}" viewBox="{} {} {} {}" width="{}""#,
+        height, x, y, width, height, width
+    )));
+
+    // Nothing drawn leaves only the margins
+    let mut renderer = ChordRenderer::new();
+    renderer.init_background();
+    assert_eq!(renderer.bounds(), None);
+    assert_eq!(renderer.view_box(), Bounds::new(-20, -20, 40, 40));
+}
@@ -0,0 +1,145 @@
+use anyhow::Result;
+use nns_chart_parser::{
+    chart::Chart,
+    line::LineElement,
+    parser::{ChartParser, ShorthandParser},
+    shorthand,
+};
+
+fn same_chart(a: &Chart, b: &Chart) -> bool {
+    serde_yaml::to_value(a).unwrap() == serde_yaml::to_value(b).unwrap()
+}
+
+#[test]
+fn test_parse_line_matches_yaml() -> Result<()> {
+    let text = ShorthandParser::parse_file("tests/fixtures/shorthand/test_line.nns")?;
+    let yaml = ChartParser::parse_file("tests/fixtures/lines/test_line.yaml")?;
+    assert!(same_chart(&text, &yaml));
+    Ok(())
+}
+
+#[test]
+fn test_round_trip_yaml_fixtures() -> Result<()> {
+    let fixtures = [
+        "tests/fixtures/lines/test_line.yaml",
+        "tests/fixtures/charts/test_chart.yaml",
+        "tests/fixtures/charts/test_numbering.yaml",
+        "tests/fixtures/charts/test_key.yaml",
+        "tests/fixtures/charts/test_capo.yaml",
+        "tests/fixtures/charts/test_roman.yaml",
+        "tests/fixtures/minor/test_tonic_numbering.yaml",
+        "tests/fixtures/minor/test_relative_numbering.yaml",
+        "tests/fixtures/modes/test_dorian.yaml",
+        "tests/fixtures/charts/test_borrowed_qualities.yaml",
+    ];
+    for fixture in fixtures {
+        let chart = ChartParser::parse_file(fixture)?;
+        let text = shorthand::write(&chart)?;
+        let parsed = ShorthandParser::parse_str(&text)?;
+        assert!(same_chart(&chart, &parsed), "{} became:\n{}", fixture, text);
+        assert_eq!(shorthand::write(&parsed)?, text);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_write_shorthand() -> Result<()> {
+    let chart = ChartParser::parse_file("tests/fixtures/lines/test_line.yaml")?;
+    assert_eq!(shorthand::write(&chart)?, "||: 1_4 . 5_1 :||\n");
+
+    let chart = ChartParser::parse_file("tests/fixtures/charts/test_roman.yaml")?;
+    let text = shorthand::write(&chart)?;
+    assert!(text.starts_with("title: Roman\nkey: G\n\n[Verse]\n"));
+    assert!(text.contains("1 6 4 57\n7 b7 5aug 27/4\n"));
+
+    // Qualities outside the key are written as the suffixes the parser reads
+    let chart = ChartParser::parse_file("tests/fixtures/charts/test_borrowed_qualities.yaml")?;
+    let text = shorthand::write(&chart)?;
+    assert!(text.ends_with("[Verse]\n1m 4m 5sus4 3aug 2dim 6sus2\n"));
+    Ok(())
+}
+
+#[test]
+fn test_parse_song() -> Result<()> {
+    let chart = ShorthandParser::parse_file("tests/fixtures/shorthand/test_song.nns")?;
+    assert_eq!(chart.title.as_deref(), Some("Shorthand Song"));
+    assert_eq!(chart.time.to_string(), "3/4");
+    assert_eq!(chart.form().to_string(), "I V C V C");
+    assert!(chart.sections[3].is_reference());
+    assert_eq!(chart.sections[3].lines.len(), 2);
+    assert_eq!(chart.measure_numbers().total(), 4 + 8 + 7 + 8 + 7);
+
+    let verse = &chart.sections[1].lines;
+    assert!(matches!(&verse[0].line[0], LineElement::Rehearsal { mark } if mark == "A"));
+    let LineElement::Measure { measure, .. } = &verse[1].line[2] else {
+        panic!("expected a split bar");
+    };
+    assert_eq!(measure.len(), 2);
+    assert_eq!(measure[0].chord.quality, "minor");
+    assert_eq!(measure[0].chord.extension.as_deref(), Some("7"));
+    assert_eq!(measure[1].chord.quality, "major");
+    let LineElement::Measure { measure, .. } = &verse[1].line[3] else {
+        panic!("expected a pushed chord");
+    };
+    assert!(measure[0].chord.push);
+    Ok(())
+}
+
+#[test]
+fn test_write_unsupported_elements() {
+    let chart = ChartParser::parse_file("tests/fixtures/charts/test_references.yaml").unwrap();
+    assert!(shorthand::write(&chart)
+        .unwrap_err()
+        .to_string()
+        .contains("last-measure overrides have no shorthand"));
+
+    let chart = ChartParser::parse_file("tests/fixtures/lines/test_annotations.yaml").unwrap();
+    assert!(shorthand::write(&chart)
+        .unwrap_err()
+        .to_string()
+        .contains("Cannot write an annotation as shorthand"));
+
+    let chart =
+        ChartParser::parse_file("tests/fixtures/charts/test_renamed_reference.yaml").unwrap();
+    assert!(shorthand::write(&chart)
+        .unwrap_err()
+        .to_string()
+        .contains("Cannot write \"Last Verse\" as shorthand"));
+}
+
+#[test]
+fn test_parse_errors() {
+    let cases = [
+        (
+            "1 9",
+            "line 1, column 3: invalid chord \"9\": expected a degree from 1 to 7",
+        ),
+        (
+            "1 4x",
+            "line 1, column 3: invalid chord \"4x\": unknown suffix \"x\"",
+        ),
+        ("1 4/9", "invalid chord \"4/9\": expected a bass degree"),
+        ("1__4", "line 1, column 1: empty chord in split bar"),
+        ("\n[Verse\n1 4", "line 2, column 1: unclosed section header"),
+        ("[Verse]\n  1 @", "line 2, column 5: empty rehearsal mark"),
+        ("key: H\n1", "line 1, column 1: invalid key: H"),
+        ("artist: Someone\n1", "unknown setting \"artist\""),
+    ];
+    for (text, expected) in cases {
+        let error = ShorthandParser::parse_str(text).unwrap_err().to_string();
+        assert!(
+            error.starts_with("Failed to parse shorthand at ") && error.contains(expected),
+            "{:?} gave {:?}",
+            text,
+            error
+        );
+    }
+}
+
+#[test]
+fn test_parse_line() -> Result<()> {
+    let line = shorthand::parse_line("||: 1 4 | 5 1 :||")?;
+    assert_eq!(line.line.len(), 6);
+    assert!(matches!(line.line[1], LineElement::Measure { .. }));
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use anyhow::Result;
+use nns_chart_parser::{
+    layout::Layout,
+    parser::{LineParser, ShorthandParser},
+    renderer::MeasureNumbering,
+    shorthand,
+    text::TextRenderer,
+};
+
+#[test]
+fn test_text_line() -> Result<()> {
+    let layout = Layout::new();
+    let text = TextRenderer::new(&layout);
+
+    let line = shorthand::parse_line("1_4 5_1 :||")?;
+    assert_eq!(
+        text.render_line(&line),
+        "| 1  4  | 5  1  :|\n  ----    ----\n"
+    );
+    let line = shorthand::parse_line("||: 1 4 :|| 5 . 6m")?;
+    assert_eq!(text.render_line(&line), "|: 1  | 4  :| 5  |    | 6m |\n");
+    Ok(())
+}
+
+#[test]
+fn test_text_annotations_and_column_width() -> Result<()> {
+    let line = LineParser::parse_file("tests/fixtures/lines/test_annotations.yaml")?;
+    let mut layout = Layout::new();
+    layout.set_key(Some("G".parse().unwrap()));
+    let mut text = TextRenderer::new(&layout);
+    text.set_column_width(6);
+
+    assert_eq!(
+        text.render_line(&line),
+        [
+            "                build … band out, vocals and acoustic guitar only",
+            "| G     | C     D     | G     |",
+            "          -------",
+            "  ...mou…",
+            "",
+        ]
+        .join("\n")
+    );
+
+    // Annotations are only cut short where the next chord needs the room
+    text.set_column_width(18);
+    let rendered = text.render_line(&line);
+    assert!(rendered.contains("build & swell"));
+    assert!(rendered.ends_with("\n  ...mountain high\n"));
+    Ok(())
+}
+
+#[test]
+fn test_text_chart() -> Result<()> {
+    let chart = ShorthandParser::parse_str(
+        "key: C\n\n[Verse]\n@A 1 4\n\n[Chorus]\n5 1\n\n[Verse]\n[Chorus]\n",
+    )?;
+    let mut layout = Layout::new();
+    layout
+        .set_expand_references(false)
+        .set_measure_numbering(MeasureNumbering::LineStarts);
+
+    assert_eq!(
+        TextRenderer::new(&layout).render_chart(&chart),
+        [
+            "Verse",
+            "1",
+            "[A]",
+            "| 1  | 4  |",
+            "",
+            "Chorus",
+            "3",
+            "| 5  | 1  |",
+            "",
+            "Verse",
+            "(Verse)",
+            "",
+            "Chorus",
+            "(Chorus)",
+            "",
+        ]
+        .join("\n")
+    );
+    Ok(())
+}
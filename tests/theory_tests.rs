@@ -0,0 +1,386 @@
+use nns_chart_parser::{
+    chord::{Accidental, Bass, Chord, ChordQuality},
+    theory::{solfege, Key, Letter, MinorNumbering, Mode, Note, MAJOR_SCALE},
+};
+
+fn key(name: &str) -> Key {
+    name.parse().unwrap()
+}
+
+#[test]
+fn test_parse_note() {
+    assert_eq!("G".parse::<Note>(), Ok(Note::new(Letter::G, 0)));
+    assert_eq!("Bb".parse::<Note>(), Ok(Note::new(Letter::B, -1)));
+    assert_eq!("f#".parse::<Note>(), Ok(Note::new(Letter::F, 1)));
+    assert!("H".parse::<Note>().is_err());
+    assert!("Cx".parse::<Note>().is_err());
+}
+
+#[test]
+fn test_note_pitch_class() {
+    assert_eq!(Note::new(Letter::C, 0).pitch_class(), 0);
+    assert_eq!(Note::new(Letter::B, -1).pitch_class(), 10);
+    assert_eq!(Note::new(Letter::B, 1).pitch_class(), 0);
+    assert_eq!(Note::new(Letter::C, -1).pitch_class(), 11);
+}
+
+#[test]
+fn test_parse_key() {
+    assert_eq!(key("Eb").to_string(), "Eb");
+    assert!(key("F").uses_flats());
+    assert!(key("Bb").uses_flats());
+    assert!(!key("D").uses_flats());
+    assert_eq!("Q".parse::<Key>().unwrap_err(), "invalid key: Q");
+}
+
+#[test]
+fn test_chord_names() {
+    assert_eq!(
+        key("G").chord_name(&Chord::new(1, ChordQuality::Major)),
+        "G"
+    );
+    assert_eq!(
+        key("C").chord_name(&Chord::new(6, ChordQuality::Minor)),
+        "Am"
+    );
+    assert_eq!(
+        key("G").chord_name(&Chord::new(7, ChordQuality::Dim)),
+        "F#dim"
+    );
+    assert_eq!(
+        key("D").chord_name(&Chord::new(4, ChordQuality::Sus4)),
+        "Gsus4"
+    );
+
+    let slash = Chord {
+        bass: Some(Bass::new(6)),
+        ..Chord::new(4, ChordQuality::Major)
+    };
+    assert_eq!(key("F").chord_name(&slash), "Bb/D");
+}
+
+#[test]
+fn test_altered_chord_names() {
+    let flat_seven = Chord {
+        accidental: Some(Accidental::Flat),
+        ..Chord::new(7, ChordQuality::Major)
+    };
+    assert_eq!(key("C").chord_name(&flat_seven), "Bb");
+    assert_eq!(key("A").chord_name(&flat_seven), "G");
+
+    let sharp_four = Chord {
+        accidental: Some(Accidental::Sharp),
+        ..Chord::new(4, ChordQuality::Dim)
+    };
+    assert_eq!(key("C").chord_name(&sharp_four), "F#dim");
+}
+
+const MAJOR_SCALES: [(&str, [&str; 7]); 15] = [
+    ("C", ["C", "D", "E", "F", "G", "A", "B"]),
+    ("G", ["G", "A", "B", "C", "D", "E", "F#"]),
+    ("D", ["D", "E", "F#", "G", "A", "B", "C#"]),
+    ("A", ["A", "B", "C#", "D", "E", "F#", "G#"]),
+    ("E", ["E", "F#", "G#", "A", "B", "C#", "D#"]),
+    ("B", ["B", "C#", "D#", "E", "F#", "G#", "A#"]),
+    ("F#", ["F#", "G#", "A#", "B", "C#", "D#", "E#"]),
+    ("C#", ["C#", "D#", "E#", "F#", "G#", "A#", "B#"]),
+    ("F", ["F", "G", "A", "Bb", "C", "D", "E"]),
+    ("Bb", ["Bb", "C", "D", "Eb", "F", "G", "A"]),
+    ("Eb", ["Eb", "F", "G", "Ab", "Bb", "C", "D"]),
+    ("Ab", ["Ab", "Bb", "C", "Db", "Eb", "F", "G"]),
+    ("Db", ["Db", "Eb", "F", "Gb", "Ab", "Bb", "C"]),
+    ("Gb", ["Gb", "Ab", "Bb", "Cb", "Db", "Eb", "F"]),
+    ("Cb", ["Cb", "Db", "Eb", "Fb", "Gb", "Ab", "Bb"]),
+];
+
+fn all_keys() -> Vec<Key> {
+    MAJOR_SCALES
+        .iter()
+        .flat_map(|(tonic, scale)| [key(tonic), key(&format!("{}m", scale[5]))])
+        .collect()
+}
+
+#[test]
+fn test_spell_major_scales() {
+    for (tonic, scale) in MAJOR_SCALES {
+        let key = key(tonic);
+        for (degree, expected) in (1..=7).zip(scale) {
+            assert_eq!(
+                key.note_name(degree, None),
+                expected,
+                "{} in {}",
+                degree,
+                tonic
+            );
+        }
+    }
+}
+
+#[test]
+fn test_relative_minor_signatures() {
+    for (tonic, scale) in MAJOR_SCALES {
+        let major = key(tonic);
+        let minor = key(&format!("{}m", scale[5]));
+        assert_eq!(minor.mode, Mode::Minor);
+        assert_eq!(minor.signature(), major.signature(), "{}", minor);
+        assert_eq!(minor.uses_flats(), major.uses_flats(), "{}", minor);
+    }
+}
+
+#[test]
+fn test_spelling_never_needs_double_accidentals() {
+    for key in all_keys() {
+        for degree in 1..=7 {
+            for accidental in [None, Some(Accidental::Flat), Some(Accidental::Sharp)] {
+                let note = key.spell(degree, accidental);
+                let alteration = accidental.map_or(0, |a| a.semitones());
+                let expected = key.tonic.pitch_class() + MAJOR_SCALE[degree as usize - 1];
+
+                assert_eq!(note.pitch_class(), (expected + alteration).rem_euclid(12));
+                assert!(note.accidental.abs() <= 1, "{} in {}", note, key);
+                if key.uses_flats() {
+                    assert!(note.accidental <= 0, "{} in {}", note, key);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_chromatic_spelling() {
+    assert_eq!(key("E").note_name(3, Some(Accidental::Flat)), "G");
+    assert_eq!(key("E").note_name(2, Some(Accidental::Flat)), "F");
+    assert_eq!(key("A").note_name(7, Some(Accidental::Flat)), "G");
+    assert_eq!(key("G").note_name(3, Some(Accidental::Flat)), "Bb");
+    assert_eq!(key("C").note_name(4, Some(Accidental::Sharp)), "F#");
+    assert_eq!(key("F").note_name(4, Some(Accidental::Sharp)), "B");
+    assert_eq!(key("F").note_name(1, Some(Accidental::Sharp)), "Gb");
+    assert_eq!(key("Bb").note_name(5, Some(Accidental::Sharp)), "Gb");
+    // Would be F## and Bbb on the degree's own letter
+    assert_eq!(key("C#").note_name(4, Some(Accidental::Sharp)), "G");
+    assert_eq!(key("Cb").note_name(7, Some(Accidental::Flat)), "A");
+}
+
+#[test]
+fn test_minor_key_spelling() {
+    assert_eq!(key("Am").note_name(1, None), "A");
+    assert_eq!(key("Am").note_name(3, Some(Accidental::Flat)), "C");
+    assert_eq!(key("Dm").note_name(6, Some(Accidental::Flat)), "Bb");
+    assert_eq!(key("C#m").note_name(7, Some(Accidental::Flat)), "B");
+    assert_eq!(key("C#m").note_name(7, None), "B#");
+}
+
+#[test]
+fn test_parse_key_with_mode() {
+    assert_eq!(key("F#m"), Key::minor(Note::new(Letter::F, 1)));
+    assert_eq!(key("C# minor"), Key::minor(Note::new(Letter::C, 1)));
+    assert_eq!(key("Ebmin"), Key::minor(Note::new(Letter::E, -1)));
+    assert_eq!(key("Bb major"), Key::new(Note::new(Letter::B, -1)));
+    assert_eq!(key("Gm").to_string(), "Gm");
+    assert_eq!(key("A#m").signature(), 7);
+    assert!("D#".parse::<Key>().is_err());
+    assert!("Fb".parse::<Key>().is_err());
+    assert!("Dbm".parse::<Key>().is_err());
+}
+
+#[test]
+fn test_chord_tones() {
+    let tones = |key: &str, chord: Chord| -> Vec<String> {
+        self::key(key)
+            .chord_tones(&chord)
+            .iter()
+            .map(|n| n.to_string())
+            .collect()
+    };
+
+    assert_eq!(
+        tones("G", Chord::new(7, ChordQuality::Dim)),
+        ["F#", "A", "C"]
+    );
+    assert_eq!(
+        tones("F", Chord::new(4, ChordQuality::Major)),
+        ["Bb", "D", "F"]
+    );
+    assert_eq!(
+        tones("E", Chord::new(6, ChordQuality::Minor)),
+        ["C#", "E", "G#"]
+    );
+    assert_eq!(
+        tones("Db", Chord::new(2, ChordQuality::Sus4)),
+        ["Eb", "Ab", "Bb"]
+    );
+    assert_eq!(
+        tones("Eb", Chord::new(5, ChordQuality::Aug)),
+        ["Bb", "D", "F#"]
+    );
+
+    let flat_three = Chord {
+        accidental: Some(Accidental::Flat),
+        ..Chord::new(3, ChordQuality::Major)
+    };
+    assert_eq!(tones("E", flat_three), ["G", "B", "D"]);
+}
+
+#[test]
+fn test_minor_numbering_conversion() {
+    let to_relative = |degree, accidental| {
+        MinorNumbering::Tonic.convert(MinorNumbering::Relative, degree, accidental)
+    };
+    assert_eq!(to_relative(1, None), (6, None));
+    assert_eq!(to_relative(4, None), (2, None));
+    assert_eq!(to_relative(5, None), (3, None));
+    assert_eq!(to_relative(3, Some(Accidental::Flat)), (1, None));
+    assert_eq!(to_relative(6, Some(Accidental::Flat)), (4, None));
+    assert_eq!(to_relative(7, Some(Accidental::Flat)), (5, None));
+    // The raised leading tone (G# in A minor) is a #5 in C
+    assert_eq!(to_relative(7, None), (5, Some(Accidental::Sharp)));
+    assert_eq!(
+        MinorNumbering::Tonic.convert(MinorNumbering::Tonic, 3, Some(Accidental::Flat)),
+        (3, Some(Accidental::Flat))
+    );
+}
+
+#[test]
+fn test_minor_numbering_round_trip() {
+    for degree in 1..=7 {
+        for accidental in [None, Some(Accidental::Flat), Some(Accidental::Sharp)] {
+            let (relative, relative_accidental) =
+                MinorNumbering::Tonic.convert(MinorNumbering::Relative, degree, accidental);
+            let back = MinorNumbering::Relative.convert(
+                MinorNumbering::Tonic,
+                relative,
+                relative_accidental,
+            );
+
+            // Same pitch in A minor either way
+            let am = key("Am");
+            assert_eq!(
+                am.spell(degree, accidental).pitch_class(),
+                am.spell(back.0, back.1).pitch_class()
+            );
+            assert_eq!(
+                am.spell(degree, accidental).pitch_class(),
+                key("C").spell(relative, relative_accidental).pitch_class()
+            );
+        }
+    }
+}
+
+#[test]
+fn test_numbering_key() {
+    assert_eq!(key("Am").relative_major(), key("C"));
+    assert_eq!(key("F#m").relative_major(), key("A"));
+    assert_eq!(key("Bbm").relative_major(), key("Db"));
+    assert_eq!(key("G").relative_major(), key("G"));
+    assert_eq!(key("Em").numbering_key(MinorNumbering::Relative), key("G"));
+    assert_eq!(key("Em").numbering_key(MinorNumbering::Tonic), key("Em"));
+}
+
+#[test]
+fn test_diatonic_qualities() {
+    let qualities = |mode: Mode| -> Vec<&str> {
+        (1..=7)
+            .map(|degree| mode.diatonic_quality(degree, None).name())
+            .collect()
+    };
+    assert_eq!(
+        qualities(Mode::Major),
+        ["major", "minor", "minor", "major", "major", "minor", "dim"]
+    );
+    // Natural degrees outside the mode fall back to major
+    assert_eq!(
+        qualities(Mode::Mixolydian),
+        ["major", "minor", "dim", "major", "minor", "minor", "major"]
+    );
+    assert_eq!(
+        Mode::Minor.diatonic_quality(3, Some(Accidental::Flat)),
+        ChordQuality::Major
+    );
+    assert_eq!(Mode::Minor.diatonic_quality(2, None), ChordQuality::Dim);
+    assert_eq!(
+        Mode::Dorian.diatonic_quality(7, Some(Accidental::Flat)),
+        ChordQuality::Major
+    );
+    assert_eq!(Mode::Lydian.diatonic_quality(2, None), ChordQuality::Major);
+    assert_eq!(Mode::Dorian.scale(), [0, 2, 3, 5, 7, 9, 10]);
+}
+
+#[test]
+fn test_parse_modal_keys() {
+    assert_eq!(
+        key("D dorian"),
+        Key::with_mode(Note::new(Letter::D, 0), Mode::Dorian)
+    );
+    assert_eq!(key("A aeolian"), key("Am"));
+    assert_eq!(key("C Ionian"), key("C"));
+    assert_eq!(key("G mixolydian").to_string(), "G mixolydian");
+    for name in [
+        "D dorian",
+        "E phrygian",
+        "F lydian",
+        "G mixolydian",
+        "B locrian",
+    ] {
+        assert_eq!(key(name).signature(), 0, "{}", name);
+        assert_eq!(key(name).relative_major(), key("C"), "{}", name);
+    }
+    assert_eq!(key("Bb mixolydian").signature(), -3);
+    assert!("D# lydian".parse::<Key>().is_err());
+    assert!("D ionic".parse::<Key>().is_err());
+}
+
+#[test]
+fn test_capo_shapes() {
+    assert_eq!(key("A").capo_shapes(2), key("G"));
+    assert_eq!(key("Eb").capo_shapes(1), key("D"));
+    assert_eq!(key("Bbm").capo_shapes(1), key("Am"));
+    assert_eq!(key("C").capo_shapes(5), key("G"));
+    assert_eq!(key("D dorian").capo_shapes(2), key("C dorian"));
+    // Enharmonic tonics take the simpler signature
+    assert_eq!(key("Ab").transpose(-2), key("Gb"));
+    assert_eq!(key("G").transpose(-1), key("F#"));
+    assert_eq!(key("Db").transpose(-1), key("C"));
+}
+
+#[test]
+fn test_solfege_syllables() {
+    let naturals: Vec<_> = (1..=7).map(|degree| solfege(degree, None)).collect();
+    assert_eq!(naturals, ["do", "re", "mi", "fa", "sol", "la", "ti"]);
+    assert_eq!(solfege(7, Some(Accidental::Flat)), "te");
+    assert_eq!(solfege(4, Some(Accidental::Sharp)), "fi");
+    assert_eq!(solfege(3, Some(Accidental::Flat)), "me");
+    assert_eq!(solfege(6, Some(Accidental::Flat)), "le");
+    assert_eq!(solfege(2, Some(Accidental::Flat)), "ra");
+}
+
+#[test]
+fn test_degree_of_note() {
+    let note = |name: &str| name.parse::<Note>().unwrap();
+    assert_eq!(key("C").degree_of(&note("Bb")), (7, Some(Accidental::Flat)));
+    assert_eq!(
+        key("C").degree_of(&note("A#")),
+        (6, Some(Accidental::Sharp))
+    );
+    assert_eq!(key("G").degree_of(&note("F#")), (7, None));
+    assert_eq!(key("Am").degree_of(&note("C")), (3, Some(Accidental::Flat)));
+    // Too far from its letter's degree, so numbered by pitch
+    assert_eq!(key("Eb").degree_of(&note("E#")), (2, None));
+
+    // Inverse of spelling for every degree in every key
+    for key in all_keys() {
+        for degree in 1..=7 {
+            for accidental in [None, Some(Accidental::Flat), Some(Accidental::Sharp)] {
+                let spelled = key.spell(degree, accidental);
+                let (d, a) = key.degree_of(&spelled);
+                assert_eq!(
+                    key.spell(d, a),
+                    spelled,
+                    "{} {:?}{}",
+                    key,
+                    accidental,
+                    degree
+                );
+            }
+        }
+    }
+}